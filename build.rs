@@ -10,7 +10,7 @@ use std::fs;
 use std::path::{Path, PathBuf};
 use std::process::Command;
 
-static BUILD_VERSION: u64 = 1;
+static BUILD_VERSION: u64 = 3;
 
 struct Zstd {
 	remote: String,
@@ -81,6 +81,9 @@ impl Zstd {
 		}
 		println!("cargo:rustc-cfg=zstd");
 		println!("cargo:rustc-link-lib=static={}", "zstd_bench");
+		if self.config.multithread {
+			println!("cargo:rustc-link-lib=pthread");
+		}
 		println!("cargo:rustc-link-search=native={}", self.out_dir.display());
 	}
 
@@ -177,6 +180,10 @@ impl Zstd {
 			let entry = entry.unwrap();
 			let path = entry.path();
 			let ext = path.extension().unwrap();
+			// src/zstd.c includes it, when present, to reach its static functions.
+			if path.ends_with("decompress/zstd_decompress_block.c") {
+				continue;
+			}
 			if ext == "c" || ext == "S" {
 				build.file(&path);
 			}
@@ -217,6 +224,10 @@ impl Zstd {
 		self.add_files(&mut build, &lib_dir, "common");
 		self.add_files(&mut build, &lib_dir, "compress");
 		self.add_files(&mut build, &lib_dir, "decompress");
+		self.add_files(&mut build, &lib_dir, "dictBuilder");
+		if self.config.multithread {
+			build.define("ZSTD_MULTITHREAD", None).flag("-pthread");
+		}
 		build.out_dir(&self.out_dir)
 			.opt_level(3)
 			.flag("-g")
@@ -239,6 +250,7 @@ struct Config {
 	cc: String,
 	cc_version: String,
 	cflags: String,
+	multithread: bool,
 	zstd_hash: u64,
 	build_version: u64,
 }
@@ -272,11 +284,13 @@ impl Config {
 		let cc = env::var("CC").unwrap_or("cc".to_owned());
 		let cc_version = get_version(&cc);
 		let cflags = env::var("CFLAGS").unwrap_or(String::new());
+		let multithread = env::var("ZSTD_MULTITHREAD").map(|mt| mt == "1").unwrap_or(false);
 		let zstd_hash = fasthash::xx::hash64(&fs::read("src/zstd.c").unwrap());
 		Config {
 			cc,
 			cc_version,
 			cflags,
+			multithread,
 			zstd_hash,
 			build_version: BUILD_VERSION,
 		}
@@ -285,6 +299,7 @@ impl Config {
 	fn print_cargo_rerun_metadata() {
 		println!("cargo:rerun-if-env-changed=CC");
 		println!("cargo:rerun-if-env-changed=CFLAGS");
+		println!("cargo:rerun-if-env-changed=ZSTD_MULTITHREAD");
 	}
 
 	fn print_cargo_rustc_metadata(&self) {
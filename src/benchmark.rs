@@ -2,7 +2,8 @@ extern crate fasthash;
 extern crate glob;
 extern crate serde;
 use crate::config::BenchmarkConfig;
-use crate::config::{Config, DataSetConfig, DataSetMode};
+use crate::config::{Config, DataSetConfig, DataSetMode, Warmup};
+use crate::perf::{Counter, Counters};
 use glob::glob;
 use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
@@ -89,6 +90,14 @@ impl DataSet {
 			data,
 		}
 	}
+
+	#[cfg(test)]
+	pub fn from_data(name: &str, data: Vec<Vec<u8>>) -> Self {
+		DataSet {
+			name: name.to_string(),
+			data: data.into_iter().map(Datum::new).collect(),
+		}
+	}
 }
 
 #[derive(Default)]
@@ -132,6 +141,17 @@ impl std::ops::Add for Metrics {
 	}
 }
 
+/// The memory used by a benchmark's contexts, reported after the runs.
+#[derive(Default)]
+pub struct MemoryUsage {
+	/// ZSTD_sizeof_CCtx() or ZSTD_sizeof_DCtx().
+	pub context_bytes: Option<u64>,
+	/// ZSTD_estimateCCtxSize_usingCCtxParams().
+	pub estimated_context_bytes: Option<u64>,
+	/// The most bytes allocated at once through a counting ZSTD_customMem.
+	pub peak_allocated_bytes: Option<u64>,
+}
+
 #[derive(Debug, PartialEq, Eq)]
 enum TimerState {
 	Stopped,
@@ -180,6 +200,17 @@ pub struct Statistic {
 	pub mean: u64,
 	pub median: u64,
 	pub std_dev: u64,
+	/// The median absolute deviation from the median.
+	#[serde(default)]
+	pub mad: u64,
+	/// The mean without the lowest and highest 10% of the samples.
+	#[serde(default)]
+	pub trimmed_mean: u64,
+	/// The 95% confidence interval of the mean, from Student's t-distribution.
+	#[serde(default)]
+	pub ci_low: u64,
+	#[serde(default)]
+	pub ci_high: u64,
 }
 
 fn median(data: &[u64]) -> u64 {
@@ -195,22 +226,79 @@ fn median(data: &[u64]) -> u64 {
 	}
 }
 
-fn std_dev(data: &[u64], mean: u64) -> u64 {
-	let variance =
-		data.iter().map(|x| (x - mean) * (x - mean)).sum::<u64>() / (data.len() as u64);
-	(variance as f64).sqrt() as u64
+/// The sum of the squared deviations from the mean.
+fn squared_deviations(data: &[u64]) -> f64 {
+	let mean = data.iter().map(|&x| x as f64).sum::<f64>() / (data.len() as f64);
+	data.iter().map(|&x| (x as f64 - mean).powi(2)).sum()
+}
+
+fn std_dev(data: &[u64]) -> u64 {
+	(squared_deviations(data) / (data.len() as f64)).sqrt() as u64
+}
+
+fn mad(data: &[u64]) -> u64 {
+	let median = median(data);
+	let deviations: Vec<_> = data
+		.iter()
+		.map(|&x| std::cmp::max(x, median) - std::cmp::min(x, median))
+		.collect();
+	self::median(&deviations)
+}
+
+fn trimmed_mean(data: &[u64]) -> u64 {
+	let mut sorted = data.to_owned();
+	sorted.sort();
+	let trim = data.len() / 10;
+	let kept = &sorted[trim..data.len() - trim];
+	kept.iter().sum::<u64>() / (kept.len() as u64)
+}
+
+/// The 97.5th percentile of Student's t-distribution with `df` degrees of
+/// freedom, rounded towards fewer degrees of freedom past 30.
+fn t_975(df: usize) -> f64 {
+	const TABLE: [f64; 30] = [
+		12.706, 4.303, 3.182, 2.776, 2.571, 2.447, 2.365, 2.306, 2.262, 2.228, 2.201, 2.179,
+		2.160, 2.145, 2.131, 2.120, 2.110, 2.101, 2.093, 2.086, 2.080, 2.074, 2.069, 2.064,
+		2.060, 2.056, 2.052, 2.048, 2.045, 2.042,
+	];
+	match df {
+		1..=30 => TABLE[df - 1],
+		31..=40 => 2.042,
+		41..=60 => 2.021,
+		61..=120 => 2.000,
+		_ => 1.980,
+	}
+}
+
+fn confidence_interval(data: &[u64], mean: u64) -> (u64, u64) {
+	if data.len() < 2 {
+		return (mean, mean);
+	}
+	let df = data.len() - 1;
+	let std_err = (squared_deviations(data) / (df as f64)).sqrt() / (data.len() as f64).sqrt();
+	let half_width = t_975(df) * std_err;
+	// Negative bounds saturate to 0.
+	(
+		(mean as f64 - half_width) as u64,
+		(mean as f64 + half_width) as u64,
+	)
 }
 
 impl Statistic {
 	pub fn compute(data: &[u64]) -> Self {
 		assert_ne!(data.len(), 0);
 		let mean = data.iter().sum::<u64>() / (data.len() as u64);
+		let (ci_low, ci_high) = confidence_interval(data, mean);
 		Statistic {
 			min: *data.iter().min().unwrap(),
 			max: *data.iter().max().unwrap(),
 			mean,
 			median: median(data),
-			std_dev: std_dev(data, mean),
+			std_dev: std_dev(data),
+			mad: mad(data),
+			trimmed_mean: trimmed_mean(data),
+			ci_low,
+			ci_high,
 		}
 	}
 }
@@ -232,13 +320,43 @@ pub struct BenchmarkResult {
 	pub config_name: Option<String>,
 
 	pub data_set: String,
+	pub bucket: Option<String>,
 
 	pub iters_per_run: u64,
 	pub runs: u64,
+	#[serde(default)]
+	pub warmup_runs: u64,
+	#[serde(default)]
+	pub warmup_ns: u64,
+	/// Whether the runs reached the target_precision, None without one.
+	#[serde(default)]
+	pub precision_reached: Option<bool>,
 
 	pub uncompressed_bytes: Option<u64>,
 	pub compressed_bytes: Option<u64>,
 	pub duration_ns: Statistic,
+	/// The duration of each run, which duration_ns summarizes.
+	#[serde(default)]
+	pub duration_ns_samples: Vec<u64>,
+
+	pub cycles: Option<Statistic>,
+	pub instructions: Option<Statistic>,
+	pub branches: Option<Statistic>,
+	pub branch_misses: Option<Statistic>,
+	pub l1d_misses: Option<Statistic>,
+	pub llc_misses: Option<Statistic>,
+
+	pub nb_workers: Option<u64>,
+	pub reuse_context: Option<bool>,
+	pub context_bytes: Option<u64>,
+	pub estimated_context_bytes: Option<u64>,
+	pub peak_allocated_bytes: Option<u64>,
+	pub output_hash: Option<u64>,
+
+	pub dictionary_hash: Option<u64>,
+	pub dictionary_size: Option<u64>,
+	pub holdout_uncompressed_bytes: Option<u64>,
+	pub holdout_compressed_bytes: Option<u64>,
 }
 
 impl BenchmarkResult {
@@ -261,6 +379,28 @@ impl BenchmarkResult {
 
 		result
 	}
+
+	pub fn counter(&self, counter: Counter) -> Option<&Statistic> {
+		match counter {
+			Counter::Cycles => self.cycles.as_ref(),
+			Counter::Instructions => self.instructions.as_ref(),
+			Counter::Branches => self.branches.as_ref(),
+			Counter::BranchMisses => self.branch_misses.as_ref(),
+			Counter::L1dMisses => self.l1d_misses.as_ref(),
+			Counter::LlcMisses => self.llc_misses.as_ref(),
+		}
+	}
+
+	fn counter_mut(&mut self, counter: Counter) -> &mut Option<Statistic> {
+		match counter {
+			Counter::Cycles => &mut self.cycles,
+			Counter::Instructions => &mut self.instructions,
+			Counter::Branches => &mut self.branches,
+			Counter::BranchMisses => &mut self.branch_misses,
+			Counter::L1dMisses => &mut self.l1d_misses,
+			Counter::LlcMisses => &mut self.llc_misses,
+		}
+	}
 }
 
 fn compute_iters_and_runs(config: &Config, benchmark: &mut dyn Benchmark, data_set: &DataSet) -> (u64, u64) {
@@ -294,6 +434,34 @@ fn compute_iters_and_runs(config: &Config, benchmark: &mut dyn Benchmark, data_s
 	}
 }
 
+/// The mean and variance of the run durations, updated after each run with
+/// Welford's algorithm, so adaptive runs don't recompute them from every
+/// sample.
+#[derive(Default)]
+struct RunningStatistic {
+	n: u64,
+	mean: f64,
+	squared_deviations: f64,
+}
+
+impl RunningStatistic {
+	fn push(&mut self, x: u64) {
+		let x = x as f64;
+		self.n += 1;
+		let delta = x - self.mean;
+		self.mean += delta / self.n as f64;
+		self.squared_deviations += delta * (x - self.mean);
+	}
+
+	/// The half width of the mean's confidence interval, relative to the
+	/// mean, like Statistic's ci_low and ci_high. Needs two samples.
+	fn relative_precision(&self) -> f64 {
+		let df = self.n - 1;
+		let std_err = (self.squared_deviations / df as f64 / self.n as f64).sqrt();
+		t_975(df as usize) * std_err / self.mean
+	}
+}
+
 fn assert_opt_eq(prev: &Option<u64>, curr: Option<u64>) -> Option<u64> {
 	if let Some(prev) = prev {
 		assert_eq!(*prev, curr.unwrap());
@@ -301,13 +469,16 @@ fn assert_opt_eq(prev: &Option<u64>, curr: Option<u64>) -> Option<u64> {
 	curr
 }
 
+/// Runs the benchmark on a data set, returning one result per bucket, or a
+/// single result if the benchmark doesn't split the data set into buckets.
 pub fn run_benchmark(
 	config: &Config,
 	benchmark_name: &str,
 	config_name: Option<&str>,
 	benchmark: &mut dyn Benchmark,
 	data_set: &DataSet,
-) -> BenchmarkResult {
+	warmup: Warmup,
+) -> Vec<BenchmarkResult> {
 	let mut result = BenchmarkResult::new(config);
 	result.benchmark_name = benchmark_name.to_owned();
 	result.config_name = config_name.map(|s| s.to_owned());
@@ -316,28 +487,144 @@ pub fn run_benchmark(
 
 	benchmark.initialize_data_set(data_set);
 
+	let buckets = benchmark.buckets();
+	let results = if buckets.is_empty() {
+		vec![measure(config, result, benchmark, data_set, warmup)]
+	} else {
+		buckets
+			.into_iter()
+			.map(|bucket| {
+				println!("bucket {}", bucket);
+				benchmark.select_bucket(&bucket);
+				let mut result = result.clone();
+				result.bucket = Some(bucket);
+				measure(config, result, benchmark, data_set, warmup)
+			})
+			.collect()
+	};
+
+	benchmark.finalize_data_set(data_set);
+
+	results
+}
+
+fn measure(
+	config: &Config,
+	mut result: BenchmarkResult,
+	benchmark: &mut dyn Benchmark,
+	data_set: &DataSet,
+	warmup: Warmup,
+) -> BenchmarkResult {
+	// Warm up before calibrating, so the CPU has reached its steady state
+	// frequency before the iterations per run are chosen. The iterations
+	// aren't known yet, so warmup runs make the fewest.
+	let warmup_duration = Duration::from_secs(warmup.secs);
+	let warmup_start = Instant::now();
+	while result.warmup_runs < warmup.runs || warmup_start.elapsed() < warmup_duration {
+		benchmark.run_data_set(data_set, config.min_iters_per_run());
+		result.warmup_runs += 1;
+	}
+	result.warmup_ns = warmup_start.elapsed().as_nanos() as u64;
+	if result.warmup_runs != 0 {
+		println!(
+			"{} warmup runs in {:.2}s",
+			result.warmup_runs,
+			result.warmup_ns as f64 / 1e9
+		);
+	}
+
 	let (iters, runs) = compute_iters_and_runs(config, benchmark, data_set);
 	result.iters_per_run = iters;
-	result.runs = runs;
 
-	println!("{} runs @ {} iters/run", result.runs, result.iters_per_run);
+	// With a target precision the runs are adaptive, and min_runs is the
+	// least needed to estimate it. The runs calibrated to fill min_secs are
+	// only used without one, so precise benchmarks can stop early.
+	let target_precision = config.target_precision();
+	let min_runs = std::cmp::max(config.min_runs(), 2);
+	let max_duration = Duration::from_secs(config.max_secs());
+	match target_precision {
+		Some(target) => println!(
+			"adaptive runs to {}% precision @ {} iters/run",
+			target * 100.,
+			result.iters_per_run
+		),
+		None => println!("{} runs @ {} iters/run", runs, result.iters_per_run),
+	}
+
+	// Counted around each whole run, so setup done by run_data_set() is
+	// included, unlike in the duration.
+	let mut counters = if config.perf_counters() {
+		Some(Counters::open())
+	} else {
+		None
+	};
+	let mut counts: Vec<Vec<u64>> = Vec::new();
 
+	let start = Instant::now();
 	let mut duration_ns = Vec::new();
+	let mut running = RunningStatistic::default();
 	let mut uncompressed_bytes = None;
 	let mut compressed_bytes = None;
-	for _ in 0..runs {
+	loop {
+		let run = duration_ns.len() as u64;
+		match target_precision {
+			Some(target) => {
+				if run >= min_runs && running.relative_precision() <= target {
+					result.precision_reached = Some(true);
+					break;
+				}
+				// Even before min_runs, so slow runs can't exceed max_secs by
+				// much.
+				if run > 0 && start.elapsed() >= max_duration {
+					result.precision_reached = Some(false);
+					break;
+				}
+			}
+			None if run == runs => break,
+			None => (),
+		}
+		if let Some(counters) = &mut counters {
+			counters.start();
+		}
 		let metrics = benchmark.run_data_set(&data_set, result.iters_per_run);
+		// Runs the counters missed are left out of their statistics.
+		if let Some(count) = counters.as_mut().and_then(|counters| counters.stop()) {
+			counts.push(count);
+		}
 		uncompressed_bytes =
 			assert_opt_eq(&uncompressed_bytes, metrics.uncompressed_size);
 		compressed_bytes =
 			assert_opt_eq(&compressed_bytes, metrics.compressed_size);
 		duration_ns.push(metrics.duration.unwrap().as_nanos() as u64);
+		running.push(*duration_ns.last().unwrap());
 	}
 	result.uncompressed_bytes = uncompressed_bytes;
 	result.compressed_bytes = compressed_bytes;
+	result.runs = duration_ns.len() as u64;
 	result.duration_ns = Statistic::compute(&duration_ns);
+	result.duration_ns_samples = duration_ns;
+	if let Some(counters) = &counters {
+		// Empty if the counters missed every run.
+		if !counts.is_empty() {
+			for (i, counter) in counters.counters().into_iter().enumerate() {
+				let values: Vec<_> = counts.iter().map(|run| run[i]).collect();
+				*result.counter_mut(counter) = Some(Statistic::compute(&values));
+			}
+		}
+	}
 
-	benchmark.finalize_data_set(data_set);
+	let memory = benchmark.memory_usage();
+	result.context_bytes = memory.context_bytes;
+	result.estimated_context_bytes = memory.estimated_context_bytes;
+	result.peak_allocated_bytes = memory.peak_allocated_bytes;
+
+	if config.verify() {
+		benchmark.verify_data_set(data_set);
+	}
+	if config.output_hash() {
+		result.output_hash = benchmark.output_hash(data_set);
+	}
+	benchmark.update_result(&mut result);
 
 	result
 }
@@ -368,6 +655,37 @@ pub trait Benchmark {
 	fn run_datum(&mut self, _datum: &Datum, _iters: u64) -> Metrics {
 		Metrics::default()
 	}
+
+	/// Checks that the benchmark's output round trips, panicking if it
+	/// doesn't. Called untimed after the runs when `verify = true`.
+	fn verify_data_set(&mut self, _data_set: &DataSet) {}
+
+	/// Names the buckets the data set is split into after initialization,
+	/// each of which is measured separately and gets its own result.
+	fn buckets(&self) -> Vec<String> {
+		Vec::new()
+	}
+
+	/// Restricts the following runs to one of the buckets().
+	fn select_bucket(&mut self, _bucket: &str) {}
+
+	/// Hashes the benchmark's output for every datum, computed untimed after
+	/// the runs when `output_hash = true`. None if the benchmark doesn't
+	/// produce compressed output.
+	fn output_hash(&mut self, _data_set: &DataSet) -> Option<u64> {
+		None
+	}
+
+	/// Reports the memory used by the benchmark's contexts, called right
+	/// after the runs. Peaks cover the runs if they are reset when the data
+	/// set is initialized.
+	fn memory_usage(&self) -> MemoryUsage {
+		MemoryUsage::default()
+	}
+
+	/// Records benchmark specific information in the result, called after
+	/// the runs but before the data set is finalized.
+	fn update_result(&self, _result: &mut BenchmarkResult) {}
 }
 
 pub trait ConfigurableBenchmark: Benchmark {
@@ -375,3 +693,28 @@ pub trait ConfigurableBenchmark: Benchmark {
 
 	fn from_config(config: &BenchmarkConfig) -> Box<dyn Benchmark>;
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn statistic_with_samples_below_the_mean() {
+		let stat = Statistic::compute(&[1, 2, 3, 10]);
+		assert_eq!(stat.mean, 4);
+		assert_eq!(stat.std_dev, 3);
+		assert_eq!(stat.mad, 1);
+		assert_eq!(stat.trimmed_mean, 4);
+		assert_eq!((stat.ci_low, stat.ci_high), (0, 10));
+	}
+
+	#[test]
+	fn running_statistic_relative_precision() {
+		let mut running = RunningStatistic::default();
+		for x in &[100, 110, 90, 100] {
+			running.push(*x);
+		}
+		// t_975(3) * sqrt(200 / 3 / 4) / 100
+		assert!((running.relative_precision() - 0.1299).abs() < 1e-4);
+	}
+}
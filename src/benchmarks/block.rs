@@ -0,0 +1,213 @@
+use crate::benchmark::{Benchmark, ConfigurableBenchmark, DataSet, Datum, Metrics, Timer};
+use crate::config::BenchmarkConfig;
+use crate::zstd;
+use super::compress::check_round_trip;
+use std::collections::HashMap;
+use std::hash::Hasher;
+
+/// A block produced by compress_block(), or the source bytes when it
+/// wasn't compressible, as a real frame would store a raw block.
+enum Block {
+	Compressed { data: Vec<u8>, size: usize },
+	Raw(usize),
+}
+
+impl Block {
+	fn compressed_size(&self) -> usize {
+		match self {
+			Block::Compressed { data, .. } => data.len(),
+			Block::Raw(size) => *size,
+		}
+	}
+}
+
+fn check(ret: usize) -> usize {
+	assert!(!zstd::is_error(ret), "{}", zstd::get_error_name(ret));
+	ret
+}
+
+/// Compresses the datum as a session of blocks at most the block size.
+fn compress_blocks(cctx: &mut zstd::CCtx, level: i32, datum: &Datum) -> Vec<Block> {
+	check(cctx.compress_begin(level, datum.len()));
+	let block_size = cctx.block_size();
+	let mut out = vec![0; zstd::compress_bound(block_size)];
+	datum
+		.bytes()
+		.chunks(block_size)
+		.map(|src| {
+			let csize = check(cctx.compress_block(&mut out, src));
+			if csize == 0 {
+				Block::Raw(src.len())
+			} else {
+				Block::Compressed {
+					data: out[..csize].to_owned(),
+					size: src.len(),
+				}
+			}
+		})
+		.collect()
+}
+
+/// Decompresses a session of blocks into `out`, returning the size.
+fn decompress_blocks(dctx: &mut zstd::DCtx, datum: &Datum, blocks: &[Block], out: &mut [u8]) -> usize {
+	dctx.decompress_begin();
+	let mut pos = 0;
+	for block in blocks {
+		match block {
+			Block::Compressed { data, size } => {
+				// The exact size, since blocks don't record it.
+				pos += check(dctx.decompress_block(&mut out[pos..pos + size], data));
+			}
+			Block::Raw(size) => {
+				let raw = &mut out[pos..pos + size];
+				raw.copy_from_slice(&datum.bytes()[pos..pos + size]);
+				check(dctx.insert_block(raw));
+				pos += size;
+			}
+		}
+	}
+	pos
+}
+
+#[derive(Eq, PartialEq)]
+enum BlockMode {
+	Compress,
+	Decompress,
+}
+
+pub struct BlockBenchmark<const MODE: i32> {
+	cctx: zstd::CCtx,
+	dctx: zstd::DCtx,
+	level: i32,
+	// Only used when benchmarking decompression.
+	blocks: HashMap<u64, Vec<Block>>,
+	out: Vec<u8>,
+}
+
+pub type CompressBlockBenchmark = BlockBenchmark<0>;
+pub type DecompressBlockBenchmark = BlockBenchmark<1>;
+
+impl<const MODE: i32> BlockBenchmark<MODE> {
+	fn mode() -> BlockMode {
+		match MODE {
+			0 => BlockMode::Compress,
+			1 => BlockMode::Decompress,
+			_ => panic!("Unsupported mode"),
+		}
+	}
+
+	/// Compresses the datum's blocks in the session started by the caller.
+	fn compress_one(&mut self, datum: &Datum) -> usize {
+		let block_size = self.cctx.block_size();
+		let mut c_size = 0;
+		for src in datum.bytes().chunks(block_size) {
+			let csize = check(self.cctx.compress_block(&mut self.out, src));
+			// Uncompressible blocks would be stored raw.
+			c_size += if csize == 0 { src.len() } else { csize };
+		}
+		c_size
+	}
+}
+
+impl<const MODE: i32> ConfigurableBenchmark for BlockBenchmark<MODE> {
+	fn name() -> String {
+		match Self::mode() {
+			BlockMode::Compress => String::from("compress_block"),
+			BlockMode::Decompress => String::from("decompress_block"),
+		}
+	}
+
+	fn from_config(config: &BenchmarkConfig) -> Box<dyn Benchmark> {
+		let level = config
+			.get_parameter("ZSTD_c_compressionLevel")
+			.map(|v| v.unwrap_integer())
+			.unwrap_or(0);
+		let bm = BlockBenchmark::<MODE> {
+			cctx: zstd::CCtx::new(),
+			dctx: zstd::DCtx::new(),
+			level: level as i32,
+			blocks: HashMap::new(),
+			out: Vec::new(),
+		};
+		Box::new(bm)
+	}
+}
+
+impl<const MODE: i32> Benchmark for BlockBenchmark<MODE> {
+	fn initialize_datum(&mut self, datum: &Datum) {
+		if Self::mode() == BlockMode::Decompress {
+			let blocks = compress_blocks(&mut self.cctx, self.level, datum);
+			self.blocks.insert(datum.id(), blocks);
+		}
+	}
+
+	fn finalize_datum(&mut self, datum: &Datum) {
+		self.blocks.remove(&datum.id());
+	}
+
+	fn run_datum(&mut self, datum: &Datum, iters: u64) -> Metrics {
+		let size = match Self::mode() {
+			BlockMode::Compress => zstd::compress_bound(zstd::BLOCK_SIZE_MAX),
+			BlockMode::Decompress => datum.len(),
+		};
+		if self.out.len() < size {
+			self.out.resize(size, 0);
+		}
+
+		let mut compressed_size = 0;
+		let mut timer = Timer::new();
+		match Self::mode() {
+			BlockMode::Compress => {
+				for _ in 0..iters {
+					// Starting the session resets the context, which would
+					// dominate the small blocks this is meant for.
+					timer.stop();
+					check(self.cctx.compress_begin(self.level, datum.len()));
+					timer.start();
+					compressed_size += self.compress_one(datum);
+				}
+			}
+			BlockMode::Decompress => {
+				let blocks = self.blocks.get(&datum.id()).unwrap();
+				for _ in 0..iters {
+					decompress_blocks(&mut self.dctx, datum, blocks, &mut self.out);
+				}
+				compressed_size = blocks.iter().map(Block::compressed_size).sum::<usize>()
+					* iters as usize;
+			}
+		}
+		let duration = timer.stop();
+
+		Metrics {
+			uncompressed_size: Some(datum.len() as u64 * iters),
+			compressed_size: Some(compressed_size as u64),
+			duration: Some(duration),
+		}
+	}
+
+	fn verify_data_set(&mut self, data_set: &DataSet) {
+		for datum in data_set.data() {
+			let blocks = compress_blocks(&mut self.cctx, self.level, datum);
+			let dctx = &mut self.dctx;
+			check_round_trip(data_set, datum, |dst| {
+				decompress_blocks(dctx, datum, &blocks, dst)
+			});
+		}
+	}
+
+	fn output_hash(&mut self, data_set: &DataSet) -> Option<u64> {
+		if Self::mode() != BlockMode::Compress {
+			return None;
+		}
+		let mut hasher = fasthash::xx::Hasher64::default();
+		for datum in data_set.data() {
+			for block in compress_blocks(&mut self.cctx, self.level, datum) {
+				match block {
+					Block::Compressed { data, .. } => hasher.write(&data),
+					Block::Raw(size) => hasher.write_usize(size),
+				}
+			}
+		}
+		Some(hasher.finish())
+	}
+}
@@ -1,18 +1,166 @@
-use crate::benchmark::{Benchmark, ConfigurableBenchmark, Datum, Metrics, Timer};
-use crate::config::BenchmarkConfig;
+use crate::benchmark::{
+	Benchmark, BenchmarkResult, ConfigurableBenchmark, DataSet, Datum, MemoryUsage, Metrics,
+	Timer,
+};
+use crate::config::{BenchmarkConfig, Parameter};
 use crate::zstd;
+use std::convert::TryFrom;
+use std::hash::Hasher;
+
+/// Collects every `ZSTD_c_*` key in the config, sorted so that the
+/// compression level is applied before the parameters that refine it.
+pub fn compression_parameters(config: &BenchmarkConfig) -> Vec<(zstd::CParameter, i32)> {
+	let mut params: Vec<_> = config
+		.parameters()
+		.filter(|(name, _)| name.starts_with("ZSTD_c_"))
+		.map(|(name, value)| {
+			let param = zstd::CParameter::from_name(name)
+				.unwrap_or_else(|| panic!("Unknown compression parameter {}", name));
+			let value = match value {
+				Parameter::Integer(i) => i32::try_from(*i)
+					.unwrap_or_else(|_| panic!("{} = {} is out of range", name, i)),
+				Parameter::Bool(b) => *b as i32,
+				Parameter::String(_) => panic!("{} must be an integer or bool", name),
+			};
+			(param, value)
+		})
+		.collect();
+	params.sort();
+	check_multithread(&params);
+	params
+}
+
+/// Panics if `params` need zstd built with ZSTD_MULTITHREAD and it wasn't,
+/// rather than leaving zstd to reject them as unsupported.
+fn check_multithread(params: &[(zstd::CParameter, i32)]) {
+	let mt_params = [
+		zstd::CParameter::NbWorkers,
+		zstd::CParameter::JobSize,
+		zstd::CParameter::OverlapLog,
+	];
+	// Single threaded builds still accept 0.
+	let mt_param = params
+		.iter()
+		.find(|(param, value)| mt_params.contains(param) && *value != 0);
+	if let Some((param, _)) = mt_param {
+		let ret = zstd::CCtx::new().set_parameter(zstd::CParameter::NbWorkers, 1);
+		if zstd::is_error(ret) {
+			panic!("{} requires multithread = true in the config", param.name());
+		}
+	}
+}
+
+pub fn nb_workers(config: &BenchmarkConfig) -> Option<u64> {
+	config
+		.get_parameter("ZSTD_c_nbWorkers")
+		.map(|v| v.unwrap_integer() as u64)
+}
+
+/// The estimated size of a CCtx with `params` applied, if zstd can estimate it.
+pub fn estimate_cctx_size(params: &[(zstd::CParameter, i32)]) -> Option<u64> {
+	let size = zstd::estimate_cctx_size(params);
+	if zstd::is_error(size) {
+		None
+	} else {
+		Some(size as u64)
+	}
+}
+
+/// Creates a CCtx with `params` applied, panicking if zstd rejects any of them.
+pub fn create_cctx(params: &[(zstd::CParameter, i32)]) -> zstd::CCtx {
+	set_parameters(zstd::CCtx::new(), params)
+}
+
+/// Like create_cctx(), but its allocations are counted, for MemoryUsage.
+/// Only for contexts reused across iterations, so the counting isn't timed
+/// on every iteration.
+pub fn create_counted_cctx(params: &[(zstd::CParameter, i32)]) -> zstd::CCtx {
+	set_parameters(zstd::CCtx::with_allocation_counter(), params)
+}
+
+fn set_parameters(mut cctx: zstd::CCtx, params: &[(zstd::CParameter, i32)]) -> zstd::CCtx {
+	for (param, value) in params {
+		let ret = cctx.set_parameter(*param, *value);
+		if zstd::is_error(ret) {
+			panic!(
+				"{} = {} rejected: {}",
+				param.name(),
+				value,
+				zstd::get_error_name(ret)
+			);
+		}
+	}
+	cctx
+}
+
+/// Panics, naming the datum and data set, unless `decompress` regenerates
+/// `datum` into the buffer it is given.
+pub fn check_round_trip(
+	data_set: &DataSet,
+	datum: &Datum,
+	decompress: impl FnOnce(&mut [u8]) -> usize,
+) {
+	// One spare byte so that overlong output is caught.
+	let mut out = vec![0; datum.len() + 1];
+	let dsize = decompress(&mut out);
+	let error = if zstd::is_error(dsize) {
+		Some(zstd::get_error_name(dsize).to_string())
+	} else if dsize != datum.len() {
+		Some(format!("decompressed {} bytes, expected {}", dsize, datum.len()))
+	} else if &out[..dsize] != datum.bytes() {
+		Some(String::from("decompressed bytes differ"))
+	} else {
+		None
+	};
+	if let Some(error) = error {
+		panic!(
+			"Datum {:016x} in data set {} does not round trip: {}",
+			datum.id(),
+			data_set.name(),
+			error
+		);
+	}
+}
 
 pub struct CompressBenchmark {
 	level: i32,
+	params: Vec<(zstd::CParameter, i32)>,
+	// Only set when reusing the context across iterations. Otherwise every
+	// iteration pays for creating a context, through ZSTD_compress() when
+	// only the level is given.
+	cctx: Option<zstd::CCtx>,
+	nb_workers: Option<u64>,
+	estimated_context_bytes: Option<u64>,
 	out: Vec<u8>,
 }
 
+fn level_only(params: &[(zstd::CParameter, i32)]) -> bool {
+	params
+		.iter()
+		.all(|(param, _)| *param == zstd::CParameter::CompressionLevel)
+}
+
 impl CompressBenchmark {
 	fn run_one(&mut self, datum: &Datum) -> usize {
-		let csize = zstd::compress(&mut self.out, datum.bytes(), self.level);
+		let csize = match &mut self.cctx {
+			Some(cctx) => cctx.compress(&mut self.out, datum.bytes()),
+			None if level_only(&self.params) => {
+				zstd::compress(&mut self.out, datum.bytes(), self.level)
+			}
+			None => create_cctx(&self.params).compress(&mut self.out, datum.bytes()),
+		};
 		assert_eq!(zstd::is_error(csize), false);
 		csize
 	}
+
+	/// Compresses each datum once, untimed, and passes the frame to `f`.
+	fn for_each_frame(&mut self, data_set: &DataSet, mut f: impl FnMut(&Datum, &[u8])) {
+		for datum in data_set.data() {
+			self.out.resize(zstd::compress_bound(datum.len()), 0);
+			let csize = self.run_one(datum);
+			f(datum, &self.out[..csize]);
+		}
+	}
 }
 
 impl ConfigurableBenchmark for CompressBenchmark {
@@ -25,15 +173,34 @@ impl ConfigurableBenchmark for CompressBenchmark {
 			.get_parameter("ZSTD_c_compressionLevel")
 			.map(|v| v.unwrap_integer())
 			.unwrap_or(0);
-		let bm = CompressBenchmark {
+		let mut bm = CompressBenchmark {
 			level: level as i32,
+			params: compression_parameters(config),
+			cctx: None,
+			nb_workers: nb_workers(config),
+			estimated_context_bytes: None,
 			out: Vec::new(),
 		};
+		// By default level-only configs keep measuring ZSTD_compress().
+		let reuse_context = config
+			.get_parameter("reuse_context")
+			.map(|v| v.unwrap_bool())
+			.unwrap_or(!level_only(&bm.params));
+		if reuse_context {
+			bm.cctx = Some(create_counted_cctx(&bm.params));
+		}
+		bm.estimated_context_bytes = estimate_cctx_size(&bm.params);
 		Box::new(bm)
 	}
 }
 
 impl Benchmark for CompressBenchmark {
+	fn initialize_data_set(&mut self, _data_set: &DataSet) {
+		if let Some(cctx) = &self.cctx {
+			cctx.reset_peak_allocated();
+		}
+	}
+
 	fn run_datum(&mut self, datum: &Datum, iters: u64) -> Metrics {
 		let cbound = zstd::compress_bound(datum.len());
 		if self.out.len() < cbound {
@@ -53,4 +220,57 @@ impl Benchmark for CompressBenchmark {
 			duration: Some(duration),
 		}
 	}
+
+	fn verify_data_set(&mut self, data_set: &DataSet) {
+		self.for_each_frame(data_set, |datum, frame| {
+			check_round_trip(data_set, datum, |dst| zstd::decompress(dst, frame));
+		});
+	}
+
+	fn output_hash(&mut self, data_set: &DataSet) -> Option<u64> {
+		let mut hasher = fasthash::xx::Hasher64::default();
+		self.for_each_frame(data_set, |_datum, frame| hasher.write(frame));
+		Some(hasher.finish())
+	}
+
+	fn memory_usage(&self) -> MemoryUsage {
+		MemoryUsage {
+			context_bytes: self.cctx.as_ref().map(|cctx| cctx.sizeof() as u64),
+			estimated_context_bytes: self.estimated_context_bytes,
+			peak_allocated_bytes: self
+				.cctx
+				.as_ref()
+				.and_then(|cctx| cctx.peak_allocated())
+				.map(|peak| peak as u64),
+		}
+	}
+
+	fn update_result(&self, result: &mut BenchmarkResult) {
+		result.nb_workers = self.nb_workers;
+		result.reuse_context = Some(self.cctx.is_some());
+	}
+}
+
+// The stub zstd produces no output to hash.
+#[cfg(all(test, zstd))]
+mod tests {
+	use super::*;
+
+	fn output_hash(parameters: Vec<(&str, Parameter)>) -> Option<u64> {
+		let config = BenchmarkConfig::with_parameters(parameters);
+		let text = b"The quick brown fox jumps over the lazy dog. ".repeat(100);
+		let data_set = DataSet::from_data("test", vec![text]);
+		let mut benchmark = CompressBenchmark::from_config(&config);
+		benchmark.initialize_data_set(&data_set);
+		benchmark.output_hash(&data_set)
+	}
+
+	#[test]
+	fn output_hash_changes_with_the_bitstream() {
+		let level = || ("ZSTD_c_compressionLevel", Parameter::Integer(3));
+		let plain = output_hash(vec![level()]);
+		let checksum = output_hash(vec![level(), ("ZSTD_c_checksumFlag", Parameter::Bool(true))]);
+		assert!(plain.is_some());
+		assert_ne!(plain, checksum);
+	}
 }
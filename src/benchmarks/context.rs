@@ -0,0 +1,125 @@
+use crate::benchmark::{Benchmark, ConfigurableBenchmark, DataSet, Metrics, Timer};
+use crate::config::BenchmarkConfig;
+use crate::zstd;
+use super::compress::{compression_parameters, create_cctx};
+
+#[derive(Eq, PartialEq)]
+enum ContextMode {
+	CCtxCreate,
+	CCtxReset,
+	DCtxCreate,
+	DCtxBegin,
+}
+
+/// Times one context operation per iteration, so the cost per operation is
+/// reported by "ns_per_iter". The data set is only used to warm up the
+/// contexts that are reused. Resetting the parameters also times setting
+/// them again, so every reset starts from the same configured context.
+pub struct ContextBenchmark<const MODE: i32> {
+	params: Vec<(zstd::CParameter, i32)>,
+	cctx: zstd::CCtx,
+	dctx: zstd::DCtx,
+	reset: zstd::ResetDirective,
+}
+
+pub type CCtxCreateBenchmark = ContextBenchmark<0>;
+pub type CCtxResetBenchmark = ContextBenchmark<1>;
+pub type DCtxCreateBenchmark = ContextBenchmark<2>;
+pub type DCtxBeginBenchmark = ContextBenchmark<3>;
+
+impl<const MODE: i32> ContextBenchmark<MODE> {
+	fn mode() -> ContextMode {
+		match MODE {
+			0 => ContextMode::CCtxCreate,
+			1 => ContextMode::CCtxReset,
+			2 => ContextMode::DCtxCreate,
+			3 => ContextMode::DCtxBegin,
+			_ => panic!("Unsupported mode"),
+		}
+	}
+
+	fn run_one(&mut self) {
+		match Self::mode() {
+			// Dropping frees the context.
+			ContextMode::CCtxCreate => drop(zstd::CCtx::new()),
+			ContextMode::CCtxReset => {
+				let ret = self.cctx.reset(self.reset);
+				assert!(!zstd::is_error(ret));
+				if self.reset != zstd::ResetDirective::SessionOnly {
+					for (param, value) in &self.params {
+						let ret = self.cctx.set_parameter(*param, *value);
+						assert!(!zstd::is_error(ret));
+					}
+				}
+			}
+			ContextMode::DCtxCreate => drop(zstd::DCtx::new()),
+			ContextMode::DCtxBegin => self.dctx.decompress_begin(),
+		}
+	}
+}
+
+impl<const MODE: i32> ConfigurableBenchmark for ContextBenchmark<MODE> {
+	fn name() -> String {
+		let name = match Self::mode() {
+			ContextMode::CCtxCreate => "cctx_create",
+			ContextMode::CCtxReset => "cctx_reset",
+			ContextMode::DCtxCreate => "dctx_create",
+			ContextMode::DCtxBegin => "dctx_begin",
+		};
+		String::from(name)
+	}
+
+	fn from_config(config: &BenchmarkConfig) -> Box<dyn Benchmark> {
+		let reset = config
+			.get_parameter("reset")
+			.map(|v| v.unwrap_string())
+			.unwrap_or("session");
+		let reset = match reset {
+			"session" => zstd::ResetDirective::SessionOnly,
+			"parameters" => zstd::ResetDirective::Parameters,
+			"session_and_parameters" => zstd::ResetDirective::SessionAndParameters,
+			_ => panic!("Unknown reset directive {}", reset),
+		};
+		let bm = ContextBenchmark::<MODE> {
+			params: compression_parameters(config),
+			cctx: zstd::CCtx::new(),
+			dctx: zstd::DCtx::new(),
+			reset,
+		};
+		Box::new(bm)
+	}
+}
+
+impl<const MODE: i32> Benchmark for ContextBenchmark<MODE> {
+	fn initialize_data_set(&mut self, data_set: &DataSet) {
+		// Compress and decompress the data set, so the reused contexts have
+		// their workspaces allocated like they would in real use.
+		self.cctx = create_cctx(&self.params);
+		self.dctx = zstd::DCtx::new();
+		for datum in data_set.data() {
+			let mut frame = vec![0; zstd::compress_bound(datum.len())];
+			let csize = self.cctx.compress(&mut frame, datum.bytes());
+			assert!(!zstd::is_error(csize));
+			frame.truncate(csize);
+			let mut out = vec![0; datum.len()];
+			let mut output = zstd::OutBuffer { dst: &mut out, pos: 0 };
+			let mut input = zstd::InBuffer { src: &frame, pos: 0 };
+			let ret = self.dctx.decompress_stream(&mut output, &mut input);
+			assert_eq!(ret, 0);
+		}
+	}
+
+	fn run_data_set(&mut self, _data_set: &DataSet, iters: u64) -> Metrics {
+		let mut timer = Timer::new();
+		for _ in 0..iters {
+			self.run_one();
+		}
+		let duration = timer.stop();
+
+		Metrics {
+			uncompressed_size: None,
+			compressed_size: None,
+			duration: Some(duration),
+		}
+	}
+}
@@ -0,0 +1,61 @@
+use crate::benchmark::{Benchmark, ConfigurableBenchmark, Datum, Metrics, Timer};
+use crate::config::BenchmarkConfig;
+use crate::zstd;
+use super::compress::{compression_parameters, create_cctx};
+use std::collections::HashMap;
+
+pub struct DecompressBenchmark {
+	cctx: zstd::CCtx,
+	frames: HashMap<u64, Vec<u8>>,
+	out: Vec<u8>,
+}
+
+impl ConfigurableBenchmark for DecompressBenchmark {
+	fn name() -> String {
+		String::from("decompress")
+	}
+
+	fn from_config(config: &BenchmarkConfig) -> Box<dyn Benchmark> {
+		let bm = DecompressBenchmark {
+			cctx: create_cctx(&compression_parameters(config)),
+			frames: HashMap::new(),
+			out: Vec::new(),
+		};
+		Box::new(bm)
+	}
+}
+
+impl Benchmark for DecompressBenchmark {
+	fn initialize_datum(&mut self, datum: &Datum) {
+		let mut frame = vec![0; zstd::compress_bound(datum.len())];
+		let csize = self.cctx.compress(&mut frame, datum.bytes());
+		assert!(!zstd::is_error(csize));
+		frame.resize(csize, 0);
+		self.frames.insert(datum.id(), frame);
+	}
+
+	fn finalize_datum(&mut self, datum: &Datum) {
+		self.frames.remove(&datum.id());
+	}
+
+	fn run_datum(&mut self, datum: &Datum, iters: u64) -> Metrics {
+		if self.out.len() < datum.len() {
+			self.out.resize(datum.len(), 0);
+		}
+		let frame = self.frames.get(&datum.id()).unwrap();
+
+		let mut timer = Timer::new();
+		for _ in 0..iters {
+			let dsize = zstd::decompress(&mut self.out, frame);
+			assert!(!zstd::is_error(dsize));
+			assert_eq!(dsize, datum.len());
+		}
+		let duration = timer.stop();
+
+		Metrics {
+			uncompressed_size: Some(datum.len() as u64 * iters),
+			compressed_size: Some(frame.len() as u64 * iters),
+			duration: Some(duration),
+		}
+	}
+}
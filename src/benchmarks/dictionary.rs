@@ -0,0 +1,395 @@
+use crate::benchmark::{
+	Benchmark, BenchmarkResult, ConfigurableBenchmark, DataSet, Metrics, Timer,
+};
+use crate::config::{BenchmarkConfig, Parameter};
+use crate::zstd;
+use super::compress::check_round_trip;
+use std::fs;
+use std::hash::Hasher;
+
+/// Samples concatenated in the layout the ZDICT training functions expect.
+#[derive(Default)]
+struct Samples {
+	data: Vec<u8>,
+	sizes: Vec<usize>,
+}
+
+impl Samples {
+	fn new<'a>(samples: impl Iterator<Item = &'a [u8]>) -> Self {
+		let mut result = Samples::default();
+		for sample in samples {
+			result.data.extend_from_slice(sample);
+			result.sizes.push(sample.len());
+		}
+		result
+	}
+}
+
+fn check_dictionary(dict_size: usize) -> usize {
+	if zstd::is_error(dict_size) {
+		panic!("Dictionary training failed: {}", zstd::get_error_name(dict_size));
+	}
+	dict_size
+}
+
+/// Trains a dictionary of at most `dict_capacity` bytes on `samples`.
+pub fn train_dictionary<'a>(
+	samples: impl Iterator<Item = &'a [u8]>,
+	dict_capacity: usize,
+) -> Vec<u8> {
+	let samples = Samples::new(samples);
+	let mut dict = vec![0; dict_capacity];
+	let dict_size = zstd::train_from_buffer(&mut dict, &samples.data, &samples.sizes);
+	dict.resize(check_dictionary(dict_size), 0);
+	dict
+}
+
+#[derive(Eq, PartialEq)]
+enum DictionaryMode {
+	Compress,
+	Decompress,
+}
+
+pub struct DictionaryBenchmark<const MODE: i32> {
+	cctx: zstd::CCtx,
+	dctx: zstd::DCtx,
+	level: i32,
+	// Loaded from the "dictionary" parameter, otherwise trained on each data set.
+	dict_file: Option<Vec<u8>>,
+	dict_size: usize,
+	dict: Vec<u8>,
+	cdict: Option<zstd::CDict>,
+	ddict: Option<zstd::DDict>,
+	c_data: Vec<Vec<u8>>,
+	out: Vec<u8>,
+}
+
+pub type CompressDictBenchmark = DictionaryBenchmark<0>;
+pub type DecompressDictBenchmark = DictionaryBenchmark<1>;
+
+impl<const MODE: i32> DictionaryBenchmark<MODE> {
+	fn mode() -> DictionaryMode {
+		match MODE {
+			0 => DictionaryMode::Compress,
+			1 => DictionaryMode::Decompress,
+			_ => panic!("Unsupported mode"),
+		}
+	}
+
+	fn run_one(&mut self, data_set: &DataSet) -> (usize, usize) {
+		let mut d_size = 0;
+		let mut c_size = 0;
+		match Self::mode() {
+			DictionaryMode::Compress => {
+				let cdict = self.cdict.as_ref().unwrap();
+				for datum in data_set.data() {
+					let csize =
+						self.cctx
+							.compress_using_cdict(&mut self.out, datum.bytes(), cdict);
+					assert!(!zstd::is_error(csize));
+					d_size += datum.len();
+					c_size += csize;
+				}
+			}
+			DictionaryMode::Decompress => {
+				let ddict = self.ddict.as_ref().unwrap();
+				for c_data in &self.c_data {
+					let dsize = self
+						.dctx
+						.decompress_using_ddict(&mut self.out, c_data, ddict);
+					assert!(!zstd::is_error(dsize));
+					d_size += dsize;
+					c_size += c_data.len();
+				}
+			}
+		}
+		(d_size, c_size)
+	}
+}
+
+impl<const MODE: i32> ConfigurableBenchmark for DictionaryBenchmark<MODE> {
+	fn name() -> String {
+		match Self::mode() {
+			DictionaryMode::Compress => String::from("compress_dict"),
+			DictionaryMode::Decompress => String::from("decompress_dict"),
+		}
+	}
+
+	fn from_config(config: &BenchmarkConfig) -> Box<dyn Benchmark> {
+		let level = config
+			.get_parameter("ZSTD_c_compressionLevel")
+			.map(|v| v.unwrap_integer())
+			.unwrap_or(0);
+		let dict_file = config
+			.get_parameter("dictionary")
+			.map(|v| fs::read(v.unwrap_string()).unwrap());
+		let dict_size = config
+			.get_parameter("dict_size")
+			.map(|v| v.unwrap_integer() as usize)
+			.unwrap_or(112640);
+		let bm = DictionaryBenchmark::<MODE> {
+			cctx: zstd::CCtx::new(),
+			dctx: zstd::DCtx::new(),
+			level: level as i32,
+			dict_file,
+			dict_size,
+			dict: Vec::new(),
+			cdict: None,
+			ddict: None,
+			c_data: Vec::new(),
+			out: Vec::new(),
+		};
+		Box::new(bm)
+	}
+}
+
+impl<const MODE: i32> Benchmark for DictionaryBenchmark<MODE> {
+	fn initialize_data_set(&mut self, data_set: &DataSet) {
+		self.dict = match &self.dict_file {
+			Some(dict) => dict.clone(),
+			None => train_dictionary(data_set.data().iter().map(|d| d.bytes()), self.dict_size),
+		};
+		let cdict = zstd::CDict::new(&self.dict, self.level);
+		self.ddict = Some(zstd::DDict::new(&self.dict));
+
+		let max_size = data_set.data().iter().map(|d| d.len()).max().unwrap();
+		self.out.resize(zstd::compress_bound(max_size), 0);
+
+		self.c_data.clear();
+		for datum in data_set.data() {
+			let mut cdata = vec![0; zstd::compress_bound(datum.len())];
+			let csize = self.cctx.compress_using_cdict(&mut cdata, datum.bytes(), &cdict);
+			assert!(!zstd::is_error(csize));
+			cdata.resize(csize, 0);
+			self.c_data.push(cdata);
+		}
+		self.cdict = Some(cdict);
+	}
+
+	fn finalize_data_set(&mut self, _data_set: &DataSet) {
+		self.cdict = None;
+		self.ddict = None;
+		self.c_data.clear();
+	}
+
+	fn run_data_set(&mut self, data_set: &DataSet, iters: u64) -> Metrics {
+		let mut timer = Timer::new();
+		let mut compressed_size = 0;
+		let mut decompressed_size = 0;
+		for _ in 0..iters {
+			let (d_size, c_size) = self.run_one(data_set);
+			decompressed_size += d_size;
+			compressed_size += c_size;
+		}
+		let duration = timer.stop();
+
+		Metrics {
+			uncompressed_size: Some(decompressed_size as u64),
+			compressed_size: Some(compressed_size as u64),
+			duration: Some(duration),
+		}
+	}
+
+	fn verify_data_set(&mut self, data_set: &DataSet) {
+		let cdict = self.cdict.as_ref().unwrap();
+		let ddict = self.ddict.as_ref().unwrap();
+		for datum in data_set.data() {
+			let csize = self
+				.cctx
+				.compress_using_cdict(&mut self.out, datum.bytes(), cdict);
+			assert!(!zstd::is_error(csize));
+			let frame = &self.out[..csize];
+			let dctx = &mut self.dctx;
+			check_round_trip(data_set, datum, |dst| {
+				dctx.decompress_using_ddict(dst, frame, ddict)
+			});
+		}
+	}
+
+	fn output_hash(&mut self, data_set: &DataSet) -> Option<u64> {
+		let mut hasher = fasthash::xx::Hasher64::default();
+		match Self::mode() {
+			DictionaryMode::Compress => {
+				let cdict = self.cdict.as_ref().unwrap();
+				for datum in data_set.data() {
+					let csize =
+						self.cctx
+							.compress_using_cdict(&mut self.out, datum.bytes(), cdict);
+					assert!(!zstd::is_error(csize));
+					hasher.write(&self.out[..csize]);
+				}
+				Some(hasher.finish())
+			}
+			DictionaryMode::Decompress => None,
+		}
+	}
+
+	fn update_result(&self, result: &mut BenchmarkResult) {
+		result.dictionary_hash = Some(fasthash::xx::hash64(&self.dict));
+		result.dictionary_size = Some(self.dict.len() as u64);
+	}
+}
+
+enum TrainAlgorithm {
+	Legacy,
+	Cover(zstd::CoverParams),
+	FastCover(zstd::FastCoverParams),
+}
+
+pub struct TrainDictBenchmark {
+	algorithm: TrainAlgorithm,
+	// Search for k and d when either is left unset.
+	optimize: bool,
+	level: i32,
+	dict_size: usize,
+	// Every holdout_every-th datum is held out of training to evaluate the
+	// trained dictionary on.
+	holdout_every: usize,
+	train: Samples,
+	test: Vec<Vec<u8>>,
+	dict: Vec<u8>,
+}
+
+impl TrainDictBenchmark {
+	fn run_one(&mut self) -> usize {
+		self.dict.resize(self.dict_size, 0);
+		let dict_size = match &self.algorithm {
+			TrainAlgorithm::Legacy => {
+				zstd::train_from_buffer(&mut self.dict, &self.train.data, &self.train.sizes)
+			}
+			TrainAlgorithm::Cover(params) => {
+				// Copied so the selected k/d don't narrow the next search
+				let mut params = *params;
+				zstd::train_from_buffer_cover(
+					&mut self.dict,
+					&self.train.data,
+					&self.train.sizes,
+					&mut params,
+					self.optimize,
+				)
+			}
+			TrainAlgorithm::FastCover(params) => {
+				let mut params = *params;
+				zstd::train_from_buffer_fast_cover(
+					&mut self.dict,
+					&self.train.data,
+					&self.train.sizes,
+					&mut params,
+					self.optimize,
+				)
+			}
+		};
+		self.dict.truncate(check_dictionary(dict_size));
+		self.train.data.len()
+	}
+}
+
+impl ConfigurableBenchmark for TrainDictBenchmark {
+	fn name() -> String {
+		String::from("train_dict")
+	}
+
+	fn from_config(config: &BenchmarkConfig) -> Box<dyn Benchmark> {
+		let get = |name| config.get_parameter(name).map(Parameter::unwrap_integer);
+		let level = get("ZSTD_c_compressionLevel").unwrap_or(0) as i32;
+		let k = get("k").unwrap_or(0) as u32;
+		let d = get("d").unwrap_or(0) as u32;
+		let steps = get("steps").unwrap_or(0) as u32;
+		let z_params = zstd::DictParams {
+			compression_level: level,
+			..Default::default()
+		};
+		let algorithm = config
+			.get_parameter("algorithm")
+			.map(|v| v.unwrap_string())
+			.unwrap_or("legacy");
+		let algorithm = match algorithm {
+			"legacy" => TrainAlgorithm::Legacy,
+			"cover" => TrainAlgorithm::Cover(zstd::CoverParams {
+				k,
+				d,
+				steps,
+				nb_threads: 1,
+				z_params,
+				..Default::default()
+			}),
+			"fastcover" => TrainAlgorithm::FastCover(zstd::FastCoverParams {
+				k,
+				d,
+				f: get("f").unwrap_or(0) as u32,
+				steps,
+				nb_threads: 1,
+				accel: get("accel").unwrap_or(0) as u32,
+				z_params,
+				..Default::default()
+			}),
+			_ => panic!("Unknown dictionary training algorithm {}", algorithm),
+		};
+		let holdout_every = get("holdout_every").unwrap_or(10) as usize;
+		assert!(holdout_every >= 2, "holdout_every must be at least 2");
+		let bm = TrainDictBenchmark {
+			algorithm,
+			optimize: k == 0 || d == 0,
+			level,
+			dict_size: get("dict_size").unwrap_or(112640) as usize,
+			holdout_every,
+			train: Samples::default(),
+			test: Vec::new(),
+			dict: Vec::new(),
+		};
+		Box::new(bm)
+	}
+}
+
+impl Benchmark for TrainDictBenchmark {
+	fn initialize_data_set(&mut self, data_set: &DataSet) {
+		let (test, train): (Vec<_>, Vec<_>) = data_set
+			.data()
+			.iter()
+			.enumerate()
+			.partition(|(i, _)| i % self.holdout_every == 0);
+		assert!(!train.is_empty() && !test.is_empty(), "Not enough samples to hold out");
+		self.train = Samples::new(train.into_iter().map(|(_, datum)| datum.bytes()));
+		self.test = test.into_iter().map(|(_, datum)| datum.bytes().to_owned()).collect();
+	}
+
+	fn finalize_data_set(&mut self, _data_set: &DataSet) {
+		self.train = Samples::default();
+		self.test.clear();
+	}
+
+	fn run_data_set(&mut self, _data_set: &DataSet, iters: u64) -> Metrics {
+		let mut timer = Timer::new();
+		let mut trained_size = 0;
+		for _ in 0..iters {
+			trained_size += self.run_one();
+		}
+		let duration = timer.stop();
+
+		// The compressed size is only known for the held out samples, so the
+		// ratio is reported separately in update_result().
+		Metrics {
+			uncompressed_size: Some(trained_size as u64),
+			compressed_size: None,
+			duration: Some(duration),
+		}
+	}
+
+	fn update_result(&self, result: &mut BenchmarkResult) {
+		let cdict = zstd::CDict::new(&self.dict, self.level);
+		let mut cctx = zstd::CCtx::new();
+		let mut uncompressed_size = 0;
+		let mut compressed_size = 0;
+		for sample in &self.test {
+			let mut out = vec![0; zstd::compress_bound(sample.len())];
+			let csize = cctx.compress_using_cdict(&mut out, sample, &cdict);
+			assert!(!zstd::is_error(csize));
+			uncompressed_size += sample.len();
+			compressed_size += csize;
+		}
+		result.dictionary_hash = Some(fasthash::xx::hash64(&self.dict));
+		result.dictionary_size = Some(self.dict.len() as u64);
+		result.holdout_uncompressed_bytes = Some(uncompressed_size as u64);
+		result.holdout_compressed_bytes = Some(compressed_size as u64);
+	}
+}
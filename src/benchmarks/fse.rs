@@ -0,0 +1,139 @@
+use crate::benchmark::{Benchmark, ConfigurableBenchmark, DataSet, Metrics, Timer};
+use crate::config::BenchmarkConfig;
+use crate::zstd;
+
+#[derive(Eq, PartialEq)]
+enum FseMode {
+	ReadNCount,
+	BuildCTable,
+	BuildDTable,
+}
+
+/// A sequences table description taken from a real compressed block.
+struct FseTable {
+	description: Vec<u8>,
+	ncount: zstd::FseNCount,
+}
+
+pub struct FseBenchmark<const MODE: i32> {
+	fse: zstd::Fse,
+	tables: Vec<FseTable>,
+	ncount: zstd::FseNCount,
+	level: i32,
+	// Only benchmark descriptions of this table type, if set.
+	table_type: Option<zstd::FseTableType>,
+}
+
+pub type FseReadNCountBenchmark = FseBenchmark<0>;
+pub type FseBuildCTableBenchmark = FseBenchmark<1>;
+pub type FseBuildDTableBenchmark = FseBenchmark<2>;
+
+impl<const MODE: i32> FseBenchmark<MODE> {
+	fn mode() -> FseMode {
+		match MODE {
+			0 => FseMode::ReadNCount,
+			1 => FseMode::BuildCTable,
+			2 => FseMode::BuildDTable,
+			_ => panic!("Unsupported mode"),
+		}
+	}
+
+	fn run_one(&mut self) -> usize {
+		let mut size = 0;
+		for table in &self.tables {
+			size += table.description.len();
+			match Self::mode() {
+				FseMode::ReadNCount => {
+					self.fse.read_ncount(&table.description, &mut self.ncount);
+				}
+				FseMode::BuildCTable => self.fse.build_ctable(&table.ncount),
+				FseMode::BuildDTable => self.fse.build_dtable(&table.ncount),
+			}
+		}
+		size
+	}
+}
+
+impl<const MODE: i32> ConfigurableBenchmark for FseBenchmark<MODE> {
+	fn name() -> String {
+		let name = match Self::mode() {
+			FseMode::ReadNCount => "fse_read_ncount",
+			FseMode::BuildCTable => "fse_build_ctable",
+			FseMode::BuildDTable => "fse_build_dtable",
+		};
+		String::from(name)
+	}
+
+	fn from_config(config: &BenchmarkConfig) -> Box<dyn Benchmark> {
+		let level = config
+			.get_parameter("ZSTD_c_compressionLevel")
+			.map(|v| v.unwrap_integer())
+			.unwrap_or(0);
+		let table_type = config.get_parameter("table").map(|v| match v.unwrap_string() {
+			"ll" => zstd::FseTableType::LiteralLengths,
+			"of" => zstd::FseTableType::Offsets,
+			"ml" => zstd::FseTableType::MatchLengths,
+			table => panic!("Unknown FSE table {}", table),
+		});
+		let bm = FseBenchmark::<MODE> {
+			fse: zstd::Fse::new(),
+			tables: Vec::new(),
+			ncount: zstd::FseNCount::new(),
+			level: level as i32,
+			table_type,
+		};
+		Box::new(bm)
+	}
+}
+
+impl<const MODE: i32> Benchmark for FseBenchmark<MODE> {
+	fn initialize_data_set(&mut self, data_set: &DataSet) {
+		self.tables.clear();
+		let mut descriptions = Vec::new();
+		for datum in data_set.data() {
+			let mut cdata = vec![0; zstd::compress_bound(datum.len())];
+			let csize = zstd::compress(&mut cdata, datum.bytes(), self.level);
+			assert!(!zstd::is_error(csize));
+			cdata.resize(csize, 0);
+
+			let nblocks = zstd::for_each_fse_table(&cdata, |description, table_type| {
+				if self.table_type.map_or(true, |t| t == table_type) {
+					descriptions.push(description.to_owned());
+				}
+				zstd::IterationCommand::Continue
+			});
+			assert!(!zstd::is_error(nblocks));
+		}
+		for description in descriptions {
+			let mut ncount = zstd::FseNCount::new();
+			self.fse.read_ncount(&description, &mut ncount);
+			self.tables.push(FseTable {
+				description,
+				ncount,
+			});
+		}
+		if self.tables.is_empty() {
+			panic!("No FSE compressed sequences tables in the dataset!");
+		}
+	}
+
+	fn finalize_data_set(&mut self, _data_set: &DataSet) {
+		self.tables.clear();
+	}
+
+	fn run_data_set(&mut self, _dataset: &DataSet, iters: u64) -> Metrics {
+		let mut timer = Timer::new();
+		let mut size = 0;
+		for _ in 0..iters {
+			size += self.run_one();
+		}
+		let duration = timer.stop();
+
+		// The size is the total size of the table descriptions processed.
+		Metrics {
+			uncompressed_size: Some(size as u64),
+			compressed_size: None,
+			duration: Some(duration),
+		}
+	}
+}
@@ -0,0 +1,285 @@
+use crate::benchmark::{Benchmark, ConfigurableBenchmark, DataSet, Metrics, Timer};
+use crate::config::BenchmarkConfig;
+use crate::zstd;
+use super::literals::quantization;
+
+#[derive(Eq, PartialEq)]
+enum HuffmanMode {
+	BuildCTable,
+	BuildDTableX1,
+	BuildDTableX2,
+	Compress1X,
+	Compress4X,
+	Decompress1X1,
+	Decompress1X2,
+	Decompress4X1,
+	Decompress4X2,
+}
+
+impl HuffmanMode {
+	fn streams(&self) -> zstd::HufStreams {
+		match self {
+			HuffmanMode::Compress1X
+			| HuffmanMode::Decompress1X1
+			| HuffmanMode::Decompress1X2 => zstd::HufStreams::SingleStream,
+			_ => zstd::HufStreams::FourStreams,
+		}
+	}
+
+	fn is_decompress(&self) -> bool {
+		matches!(
+			self,
+			HuffmanMode::Decompress1X1
+				| HuffmanMode::Decompress1X2
+				| HuffmanMode::Decompress4X1
+				| HuffmanMode::Decompress4X2
+		)
+	}
+
+	fn decompress_mode(&self) -> zstd::HufDecompressMode {
+		match self {
+			HuffmanMode::BuildDTableX2
+			| HuffmanMode::Decompress1X2
+			| HuffmanMode::Decompress4X2 => zstd::HufDecompressMode::DoubleSymbol,
+			_ => zstd::HufDecompressMode::SingleSymbol,
+		}
+	}
+}
+
+/// A literals block re-encoded with our own Huffman table, so the table
+/// description and streams are isolated from the literals header.
+struct HufBlock {
+	literals: Vec<u8>,
+	count: Vec<u32>,
+	ctable: zstd::HufCTable,
+	// Empty unless benchmarking decompression.
+	dtable: Vec<u32>,
+	table: Vec<u8>,
+	streams: Vec<u8>,
+}
+
+pub struct HuffmanBenchmark<const MODE: i32> {
+	huf: zstd::Huffman,
+	blocks: Vec<HufBlock>,
+	ctable: zstd::HufCTable,
+	dtable: Vec<u32>,
+	out: Vec<u8>,
+	quantization: Option<i32>,
+	level: i32,
+}
+
+pub type HuffmanBuildCTableBenchmark = HuffmanBenchmark<0>;
+pub type HuffmanBuildDTableX1Benchmark = HuffmanBenchmark<1>;
+pub type HuffmanBuildDTableX2Benchmark = HuffmanBenchmark<2>;
+pub type HuffmanCompress1XBenchmark = HuffmanBenchmark<3>;
+pub type HuffmanCompress4XBenchmark = HuffmanBenchmark<4>;
+pub type HuffmanDecompress1X1Benchmark = HuffmanBenchmark<5>;
+pub type HuffmanDecompress1X2Benchmark = HuffmanBenchmark<6>;
+pub type HuffmanDecompress4X1Benchmark = HuffmanBenchmark<7>;
+pub type HuffmanDecompress4X2Benchmark = HuffmanBenchmark<8>;
+
+fn histogram(literals: &[u8]) -> Vec<u32> {
+	let mut count = vec![0; 256];
+	for &lit in literals {
+		count[lit as usize] += 1;
+	}
+	while count.last() == Some(&0) {
+		count.pop();
+	}
+	count
+}
+
+impl<const MODE: i32> HuffmanBenchmark<MODE> {
+	fn mode() -> HuffmanMode {
+		match MODE {
+			0 => HuffmanMode::BuildCTable,
+			1 => HuffmanMode::BuildDTableX1,
+			2 => HuffmanMode::BuildDTableX2,
+			3 => HuffmanMode::Compress1X,
+			4 => HuffmanMode::Compress4X,
+			5 => HuffmanMode::Decompress1X1,
+			6 => HuffmanMode::Decompress1X2,
+			7 => HuffmanMode::Decompress4X1,
+			8 => HuffmanMode::Decompress4X2,
+			_ => panic!("Unsupported mode"),
+		}
+	}
+
+	fn new(level: i32, quantization: Option<i32>) -> Self {
+		HuffmanBenchmark {
+			huf: zstd::Huffman::new(),
+			blocks: Vec::new(),
+			ctable: zstd::Huffman::new_ctable(None, None),
+			dtable: zstd::Huffman::new_dtable(None),
+			out: Vec::new(),
+			quantization,
+			level,
+		}
+	}
+
+	fn new_block(&mut self, literals: &[u8]) -> Option<HufBlock> {
+		let count = histogram(literals);
+		if count.iter().filter(|&&c| c != 0).count() < 2 {
+			// Single symbol blocks are RLE, not Huffman compressed.
+			return None;
+		}
+		let mut ctable = zstd::Huffman::new_ctable(None, None);
+		self.huf.build_ctable(&mut ctable, &count);
+
+		let mut table = vec![0; 512];
+		let table_size = self.huf.write_ctable(&mut table, &ctable);
+		table.truncate(table_size);
+
+		let mut streams = vec![0; 2 * literals.len() + 64];
+		let streams_size =
+			self.huf
+				.compress(&mut streams, literals, &ctable, Self::mode().streams());
+		if streams_size == 0 {
+			// Not compressible with this table.
+			return None;
+		}
+		streams.truncate(streams_size);
+
+		// Only the decompression benchmarks need a DTable per block. Sized for
+		// HUF_TABLELOG_MAX like zstd's decoder, which the table is read into.
+		let mut dtable = Vec::new();
+		if Self::mode().is_decompress() {
+			dtable = zstd::Huffman::new_dtable(None);
+			self.huf
+				.read_dtable(&table, &mut dtable, Self::mode().decompress_mode());
+		}
+
+		Some(HufBlock {
+			literals: literals.to_owned(),
+			count,
+			ctable,
+			dtable,
+			table,
+			streams,
+		})
+	}
+
+	fn run_one(&mut self) -> (usize, usize) {
+		let mode = Self::mode();
+		let mut d_size = 0;
+		let mut c_size = 0;
+		for block in &self.blocks {
+			d_size += block.literals.len();
+			c_size += block.table.len() + block.streams.len();
+			match mode {
+				HuffmanMode::BuildCTable => {
+					self.huf.build_ctable(&mut self.ctable, &block.count);
+				}
+				HuffmanMode::BuildDTableX1 | HuffmanMode::BuildDTableX2 => {
+					self.huf
+						.read_dtable(&block.table, &mut self.dtable, mode.decompress_mode());
+				}
+				HuffmanMode::Compress1X | HuffmanMode::Compress4X => {
+					self.huf.compress(
+						&mut self.out,
+						&block.literals,
+						&block.ctable,
+						mode.streams(),
+					);
+				}
+				HuffmanMode::Decompress1X1
+				| HuffmanMode::Decompress1X2
+				| HuffmanMode::Decompress4X1
+				| HuffmanMode::Decompress4X2 => {
+					// The regenerated size must be exact.
+					let dst = &mut self.out[..block.literals.len()];
+					self.huf
+						.decompress(dst, &block.streams, &block.dtable, mode.streams());
+				}
+			}
+		}
+		(d_size, c_size)
+	}
+}
+
+impl<const MODE: i32> ConfigurableBenchmark for HuffmanBenchmark<MODE> {
+	fn name() -> String {
+		let name = match Self::mode() {
+			HuffmanMode::BuildCTable => "huf_build_ctable",
+			HuffmanMode::BuildDTableX1 => "huf_build_dtable_x1",
+			HuffmanMode::BuildDTableX2 => "huf_build_dtable_x2",
+			HuffmanMode::Compress1X => "huf_compress_1x",
+			HuffmanMode::Compress4X => "huf_compress_4x",
+			HuffmanMode::Decompress1X1 => "huf_decompress_1x1",
+			HuffmanMode::Decompress1X2 => "huf_decompress_1x2",
+			HuffmanMode::Decompress4X1 => "huf_decompress_4x1",
+			HuffmanMode::Decompress4X2 => "huf_decompress_4x2",
+		};
+		String::from(name)
+	}
+
+	fn from_config(config: &BenchmarkConfig) -> Box<dyn Benchmark> {
+		let level = config
+			.get_parameter("ZSTD_c_compressionLevel")
+			.map(|v| v.unwrap_integer())
+			.unwrap_or(0);
+		let quantization = config
+			.get_parameter("Q")
+			.map(|v| v.unwrap_integer() as i32);
+		let bm = HuffmanBenchmark::<MODE>::new(level as i32, quantization);
+		Box::new(bm)
+	}
+}
+
+impl<const MODE: i32> Benchmark for HuffmanBenchmark<MODE> {
+	fn initialize_data_set(&mut self, data_set: &DataSet) {
+		println!("Initializing dataset...");
+		self.blocks.clear();
+		let mut literals = Vec::new();
+		for datum in data_set.data() {
+			let mut cdata = vec![0; zstd::compress_bound(datum.len())];
+			let csize = zstd::compress(&mut cdata, datum.bytes(), self.level);
+			assert!(!zstd::is_error(csize));
+			cdata.resize(csize, 0);
+
+			let nblocks = zstd::for_each_literals_block(&cdata, |c_lits, d_lits, lits_type| {
+				let huffman = lits_type == zstd::LiteralsBlockType::Compressed
+					|| lits_type == zstd::LiteralsBlockType::Repeat;
+				let q = quantization(c_lits, d_lits);
+				if huffman && self.quantization.map_or(true, |quant| quant == q) {
+					literals.push(d_lits.to_owned());
+				}
+				zstd::IterationCommand::Continue
+			});
+			assert!(!zstd::is_error(nblocks));
+		}
+		for lits in &literals {
+			if let Some(block) = self.new_block(lits) {
+				self.blocks.push(block);
+			}
+		}
+		if self.blocks.is_empty() {
+			panic!("No Huffman compressed literals in the dataset!");
+		}
+		let max_size = self.blocks.iter().map(|b| b.literals.len()).max().unwrap();
+		self.out.resize(2 * max_size + 64, 0);
+		println!("initialized");
+	}
+
+	fn finalize_data_set(&mut self, _data_set: &DataSet) {
+		self.blocks.clear();
+	}
+
+	fn run_data_set(&mut self, _dataset: &DataSet, iters: u64) -> Metrics {
+		let mut timer = Timer::new();
+		let mut compressed_size = 0;
+		let mut decompressed_size = 0;
+		for _ in 0..iters {
+			let (d_size, c_size) = self.run_one();
+			decompressed_size += d_size;
+			compressed_size += c_size;
+		}
+		let duration = timer.stop();
+
+		Metrics {
+			uncompressed_size: Some(decompressed_size as u64),
+			compressed_size: Some(compressed_size as u64),
+			duration: Some(duration),
+		}
+	}
+}
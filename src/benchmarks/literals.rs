@@ -2,19 +2,68 @@ use crate::benchmark::{Benchmark, ConfigurableBenchmark, DataSet, Metrics, Timer
 use crate::config::BenchmarkConfig;
 use crate::zstd;
 
+/// Buckets a literals block by its compression ratio into [0, 15].
+pub fn quantization(c_lits: &[u8], d_lits: &[u8]) -> i32 {
+	if c_lits.len() >= d_lits.len() {
+		15
+	} else {
+		(c_lits.len() * 16 / d_lits.len()) as i32
+	}
+}
+
 #[derive(Eq, PartialEq)]
 enum LiteralsMode {
 	Compress,
 	Decompress,
 }
 
+/// How literals blocks are split into buckets, each getting its own result.
+#[derive(Clone, Copy)]
+enum Bucketing {
+	Ratio,
+	RatioAndSize,
+}
+
+impl Bucketing {
+	fn bucket(&self, c_lits: &[u8], d_lits: &[u8]) -> String {
+		let q = format!("Q{:02}", quantization(c_lits, d_lits));
+		match self {
+			Bucketing::Ratio => q,
+			Bucketing::RatioAndSize => {
+				// Power of two size classes, named by their lower bound.
+				let size = d_lits.len();
+				let size_class = if size == 0 {
+					0
+				} else {
+					1 << (usize::BITS - 1 - size.leading_zeros())
+				};
+				format!("{}_{}", q, size_class)
+			}
+		}
+	}
+}
+
+/// A literals block kept when bucketing, so any bucket can be selected. Its
+/// literals are moved out when its bucket is selected, since each bucket is
+/// selected once.
+struct LiteralsBlock {
+	frame: usize,
+	bucket: String,
+	compressed: bool,
+	c_literals: Vec<u8>,
+	d_literals: Vec<u8>,
+}
+
 pub struct LiteralsBenchmark<const MODE: i32> {
 	compressor: zstd::LiteralsBlockCompressor,
 	decompressor: zstd::LiteralsBlockDecompressor,
 	c_data: Vec<Vec<u8>>,
 	c_literals: Vec<Vec<u8>>,
-	d_literals: Vec<Vec<u8>>,
+	// Grouped by frame, so carried tables are reset at each frame.
+	d_literals: Vec<Vec<Vec<u8>>>,
 	quantization: Option<i32>,
+	bucketing: Option<Bucketing>,
+	blocks: Vec<LiteralsBlock>,
 	level: i32,
 }
 
@@ -30,33 +79,48 @@ impl<const MODE: i32> LiteralsBenchmark<MODE> {
 		}
 	}
 
-	fn new(level: i32, quantization: Option<i32>) -> Self {
+	fn new(
+		level: i32,
+		quantization: Option<i32>,
+		bucketing: Option<Bucketing>,
+		params: zstd::LiteralsCompressionParams,
+	) -> Self {
 		LiteralsBenchmark {
-			compressor: zstd::LiteralsBlockCompressor::new(),
+			compressor: zstd::LiteralsBlockCompressor::new(params),
 			decompressor: zstd::LiteralsBlockDecompressor::new(),
 			c_data: Vec::new(),
 			c_literals: Vec::new(),
 			d_literals: Vec::new(),
 			level,
 			quantization,
+			bucketing,
+			blocks: Vec::new(),
 		}
 	}
 
+	/// Only Huffman compressed blocks are decompressed per bucket.
+	fn in_bucket(block: &LiteralsBlock) -> bool {
+		Self::mode() == LiteralsMode::Compress || block.compressed
+	}
+
 	fn run_one(&mut self) -> (usize, usize) {
 		match Self::mode() {
 			LiteralsMode::Compress => {
 				let mut d_size = 0;
 				let mut c_size = 0;
-				for d_lits in &self.d_literals {
-					d_size += d_lits.len();
-					c_size += self.compressor.compress(&d_lits);
+				for frame in &self.d_literals {
+					self.compressor.reset();
+					for d_lits in frame {
+						d_size += d_lits.len();
+						c_size += self.compressor.compress(d_lits);
+					}
 				}
 				(d_size, c_size)
 			},
 			LiteralsMode::Decompress => {
 				let mut d_size = 0;
 				let mut c_size = 0;
-				if self.quantization.is_none() {
+				if self.quantization.is_none() && self.bucketing.is_none() {
 					for c_data in &self.c_data {
 						zstd::for_each_literals_block(&c_data, |c_lits, d_lits, _lits_type| {
 							d_size += d_lits.len();
@@ -92,7 +156,31 @@ impl<const MODE: i32> ConfigurableBenchmark for LiteralsBenchmark<MODE> {
 		let quantization = config
 			.get_parameter("Q")
 			.map(|v| v.unwrap_integer() as i32);
-		let bm = LiteralsBenchmark::<MODE>::new(level as i32, quantization);
+		let defaults = zstd::LiteralsCompressionParams::default();
+		let strategy = config.get_parameter("strategy").map(|v| v.unwrap_integer());
+		if let Some(strategy) = strategy {
+			if !(1..=9).contains(&strategy) {
+				panic!("strategy = {} must be in 1..=9", strategy);
+			}
+		}
+		let params = zstd::LiteralsCompressionParams {
+			strategy: strategy.map_or(defaults.strategy, |strategy| strategy as i32),
+			suspect_uncompressible: config
+				.get_parameter("suspect_uncompressible")
+				.map_or(defaults.suspect_uncompressible, |v| v.unwrap_bool()),
+			carry_tables: config
+				.get_parameter("carry_tables")
+				.map_or(defaults.carry_tables, |v| v.unwrap_bool()),
+		};
+		let bucketing = config.get_parameter("buckets").map(|v| match v.unwrap_string() {
+			"ratio" => Bucketing::Ratio,
+			"ratio_size" => Bucketing::RatioAndSize,
+			buckets => panic!("Unknown literals buckets {}", buckets),
+		});
+		if quantization.is_some() && bucketing.is_some() {
+			panic!("Q and buckets can't be used together");
+		}
+		let bm = LiteralsBenchmark::<MODE>::new(level as i32, quantization, bucketing, params);
 		Box::new(bm)
 	}
 }
@@ -103,7 +191,8 @@ impl<const MODE: i32> Benchmark for LiteralsBenchmark<MODE> {
 		self.c_data.clear();
 		self.c_literals.clear();
 		self.d_literals.clear();
-		for datum in data_set.data() {
+		self.blocks.clear();
+		for (frame, datum) in data_set.data().iter().enumerate() {
 			let mut cdata = Vec::new();
 			cdata.resize(zstd::compress_bound(datum.len()), 0);
 			let csize = zstd::compress(&mut cdata, datum.bytes(), self.level);
@@ -115,15 +204,28 @@ impl<const MODE: i32> Benchmark for LiteralsBenchmark<MODE> {
 			});
 			assert_eq!(zstd::is_error(nblocks), false);
 
+			let mut frame_literals = Vec::new();
 			let nblocks = zstd::for_each_literals_block(&cdata, |c_lits, d_lits, lits_type| {
-				let q = if c_lits.len() >= d_lits.len() { 15 } else { (c_lits.len() * 16 / d_lits.len()) as i32 };
+				// The literals are only kept in the blocks until a bucket is selected.
+				if let Some(bucketing) = self.bucketing {
+					self.blocks.push(LiteralsBlock {
+						frame,
+						bucket: bucketing.bucket(c_lits, d_lits),
+						compressed: lits_type == zstd::LiteralsBlockType::Compressed,
+						c_literals: c_lits.to_owned(),
+						d_literals: d_lits.to_owned(),
+					});
+					return zstd::IterationCommand::Continue;
+				}
+				let q = quantization(c_lits, d_lits);
 				if lits_type == zstd::LiteralsBlockType::Compressed && self.quantization == Some(q) {
 					self.c_literals.push(c_lits.to_owned());
 				}
-				self.d_literals.push(d_lits.to_owned());
+				frame_literals.push(d_lits.to_owned());
 				zstd::IterationCommand::Continue
 			});
 			assert_eq!(zstd::is_error(nblocks), false);
+			self.d_literals.push(frame_literals);
 
 			self.c_data.push(cdata);
 		}
@@ -133,6 +235,45 @@ impl<const MODE: i32> Benchmark for LiteralsBenchmark<MODE> {
 		println!("initialized");
 	}
 
+	fn finalize_data_set(&mut self, _data_set: &DataSet) {
+		self.blocks.clear();
+	}
+
+	fn buckets(&self) -> Vec<String> {
+		let mut buckets: Vec<_> = self
+			.blocks
+			.iter()
+			.filter(|block| Self::in_bucket(block))
+			.map(|block| block.bucket.clone())
+			.collect();
+		buckets.sort();
+		buckets.dedup();
+		buckets
+	}
+
+	fn select_bucket(&mut self, bucket: &str) {
+		self.c_literals.clear();
+		self.d_literals.clear();
+		let mut prev_frame = None;
+		for block in &mut self.blocks {
+			if block.bucket != bucket || !Self::in_bucket(block) {
+				continue;
+			}
+			if block.compressed {
+				self.c_literals.push(std::mem::take(&mut block.c_literals));
+			}
+			// Carried tables follow the bucket's blocks within each frame.
+			if prev_frame != Some(block.frame) {
+				self.d_literals.push(Vec::new());
+				prev_frame = Some(block.frame);
+			}
+			self.d_literals
+				.last_mut()
+				.unwrap()
+				.push(std::mem::take(&mut block.d_literals));
+		}
+	}
+
 	fn run_data_set(&mut self, _dataset: &DataSet, iters: u64) -> Metrics {
 		let mut timer = Timer::new();
 		let mut compressed_size = 0;
@@ -2,8 +2,15 @@ use crate::benchmark::{Benchmark, ConfigurableBenchmark};
 use crate::config::BenchmarkConfig;
 use std::collections::HashMap;
 
+mod block;
 mod compress;
+mod context;
+mod decompress;
+mod dictionary;
+mod fse;
 mod literals;
+mod sequences;
+mod stream;
 mod huffman;
 
 type BenchmarkMap = HashMap<String, Box<dyn Fn(&BenchmarkConfig) -> Box<dyn Benchmark>>>;
@@ -17,9 +24,24 @@ pub fn get_all_benchmarks() -> BenchmarkMap {
 	let mut benchmarks = HashMap::new();
 
 	add::<compress::CompressBenchmark>(&mut benchmarks);
+	add::<decompress::DecompressBenchmark>(&mut benchmarks);
+	add::<stream::CompressStreamBenchmark>(&mut benchmarks);
+	add::<stream::DecompressStreamBenchmark>(&mut benchmarks);
+	add::<block::CompressBlockBenchmark>(&mut benchmarks);
+	add::<block::DecompressBlockBenchmark>(&mut benchmarks);
+
+	add::<context::CCtxCreateBenchmark>(&mut benchmarks);
+	add::<context::CCtxResetBenchmark>(&mut benchmarks);
+	add::<context::DCtxCreateBenchmark>(&mut benchmarks);
+	add::<context::DCtxBeginBenchmark>(&mut benchmarks);
+
+	add::<dictionary::CompressDictBenchmark>(&mut benchmarks);
+	add::<dictionary::DecompressDictBenchmark>(&mut benchmarks);
+	add::<dictionary::TrainDictBenchmark>(&mut benchmarks);
 
 	add::<literals::CompressLiteralsBenchmark>(&mut benchmarks);
 	add::<literals::DecompressLiteralsBenchmark>(&mut benchmarks);
+	add::<sequences::DecompressSequencesBenchmark>(&mut benchmarks);
 
 	add::<huffman::HuffmanBuildCTableBenchmark>(&mut benchmarks);
 	add::<huffman::HuffmanBuildDTableX1Benchmark>(&mut benchmarks);
@@ -31,5 +53,9 @@ pub fn get_all_benchmarks() -> BenchmarkMap {
 	add::<huffman::HuffmanDecompress4X1Benchmark>(&mut benchmarks);
 	add::<huffman::HuffmanDecompress4X2Benchmark>(&mut benchmarks);
 
+	add::<fse::FseReadNCountBenchmark>(&mut benchmarks);
+	add::<fse::FseBuildCTableBenchmark>(&mut benchmarks);
+	add::<fse::FseBuildDTableBenchmark>(&mut benchmarks);
+
 	benchmarks
 }
@@ -0,0 +1,97 @@
+use crate::benchmark::{Benchmark, ConfigurableBenchmark, DataSet, Metrics, Timer};
+use crate::config::BenchmarkConfig;
+use crate::zstd;
+
+pub struct DecompressSequencesBenchmark {
+	dctx: zstd::DCtx,
+	// The sequences sections of each frame, in block order.
+	sequences: Vec<Vec<Vec<u8>>>,
+	level: i32,
+}
+
+impl DecompressSequencesBenchmark {
+	fn run_one(&mut self) -> (usize, usize) {
+		let mut d_size = 0;
+		let mut c_size = 0;
+		for frame in &self.sequences {
+			// Sections may repeat the previous block's tables, so each frame
+			// is decoded from the start.
+			self.dctx.decompress_begin();
+			for section in frame {
+				let dsize = self.dctx.decode_sequences(section);
+				assert!(!zstd::is_error(dsize));
+				d_size += dsize;
+				c_size += section.len();
+			}
+		}
+		(d_size, c_size)
+	}
+}
+
+impl ConfigurableBenchmark for DecompressSequencesBenchmark {
+	fn name() -> String {
+		String::from("decompress_sequences")
+	}
+
+	fn from_config(config: &BenchmarkConfig) -> Box<dyn Benchmark> {
+		let level = config
+			.get_parameter("ZSTD_c_compressionLevel")
+			.map(|v| v.unwrap_integer())
+			.unwrap_or(0);
+		let bm = DecompressSequencesBenchmark {
+			dctx: zstd::DCtx::new(),
+			sequences: Vec::new(),
+			level: level as i32,
+		};
+		Box::new(bm)
+	}
+}
+
+impl Benchmark for DecompressSequencesBenchmark {
+	fn initialize_data_set(&mut self, data_set: &DataSet) {
+		self.sequences.clear();
+		for datum in data_set.data() {
+			let mut cdata = vec![0; zstd::compress_bound(datum.len())];
+			let csize = zstd::compress(&mut cdata, datum.bytes(), self.level);
+			assert!(!zstd::is_error(csize));
+			cdata.resize(csize, 0);
+
+			let mut sections = Vec::new();
+			let nblocks = zstd::for_each_sequences_block(&cdata, |section| {
+				sections.push(section.to_owned());
+				zstd::IterationCommand::Continue
+			});
+			assert!(!zstd::is_error(nblocks));
+			if !sections.is_empty() {
+				self.sequences.push(sections);
+			}
+		}
+		if self.sequences.is_empty() {
+			panic!("No compressed blocks in the dataset!");
+		}
+	}
+
+	fn finalize_data_set(&mut self, _data_set: &DataSet) {
+		self.sequences.clear();
+	}
+
+	fn run_data_set(&mut self, _dataset: &DataSet, iters: u64) -> Metrics {
+		let mut timer = Timer::new();
+		let mut compressed_size = 0;
+		let mut decompressed_size = 0;
+		for _ in 0..iters {
+			let (d_size, c_size) = self.run_one();
+			decompressed_size += d_size;
+			compressed_size += c_size;
+		}
+		let duration = timer.stop();
+
+		// The uncompressed size counts the bytes the sequences cover, and the
+		// compressed size the sequences sections.
+		Metrics {
+			uncompressed_size: Some(decompressed_size as u64),
+			compressed_size: Some(compressed_size as u64),
+			duration: Some(duration),
+		}
+	}
+}
@@ -0,0 +1,250 @@
+use crate::benchmark::{
+	Benchmark, BenchmarkResult, ConfigurableBenchmark, DataSet, Datum, MemoryUsage, Metrics,
+	Timer,
+};
+use crate::config::BenchmarkConfig;
+use crate::zstd;
+use super::compress::{
+	check_round_trip, compression_parameters, create_cctx, create_counted_cctx,
+	estimate_cctx_size, nb_workers,
+};
+use std::hash::Hasher;
+
+fn get_size(config: &BenchmarkConfig, name: &str) -> Option<usize> {
+	config.get_parameter(name).map(|v| {
+		let size = v.unwrap_integer();
+		assert!(size > 0, "{} must be positive", name);
+		size as usize
+	})
+}
+
+pub struct CompressStreamBenchmark {
+	cctx: zstd::CCtx,
+	in_chunk: usize,
+	// Issue a ZSTD_e_flush once at least this many bytes have been passed
+	// in since the last flush, otherwise only ZSTD_e_continue is used.
+	flush_every: Option<usize>,
+	nb_workers: Option<u64>,
+	estimated_context_bytes: Option<u64>,
+	// Sized to the out_chunk parameter.
+	out: Vec<u8>,
+}
+
+impl CompressStreamBenchmark {
+	/// Compresses the datum, passing each piece of output to `consume`.
+	fn run_one(&mut self, datum: &Datum, mut consume: impl FnMut(&[u8])) -> usize {
+		let src = datum.bytes();
+		let mut output = zstd::OutBuffer {
+			dst: &mut self.out,
+			pos: 0,
+		};
+		let mut compressed_size = 0;
+		let mut since_flush = 0;
+		let mut start = 0;
+		loop {
+			let end = std::cmp::min(start + self.in_chunk, src.len());
+			let last = end == src.len();
+			since_flush += end - start;
+			let end_op = if last {
+				zstd::EndDirective::End
+			} else if self.flush_every.map_or(false, |n| since_flush >= n) {
+				since_flush = 0;
+				zstd::EndDirective::Flush
+			} else {
+				zstd::EndDirective::Continue
+			};
+
+			let mut input = zstd::InBuffer {
+				src: &src[start..end],
+				pos: 0,
+			};
+			loop {
+				let ret = self.cctx.compress_stream(&mut output, &mut input, end_op);
+				assert!(!zstd::is_error(ret));
+				// The output is consumed as soon as it is produced.
+				consume(&output.dst[..output.pos]);
+				compressed_size += output.pos;
+				output.pos = 0;
+				let done = match end_op {
+					zstd::EndDirective::Continue => input.pos == input.src.len(),
+					zstd::EndDirective::Flush | zstd::EndDirective::End => ret == 0,
+				};
+				if done {
+					break;
+				}
+			}
+
+			if last {
+				return compressed_size;
+			}
+			start = end;
+		}
+	}
+}
+
+impl ConfigurableBenchmark for CompressStreamBenchmark {
+	fn name() -> String {
+		String::from("compress_stream")
+	}
+
+	fn from_config(config: &BenchmarkConfig) -> Box<dyn Benchmark> {
+		let out_chunk = get_size(config, "out_chunk").unwrap_or_else(zstd::cstream_out_size);
+		let params = compression_parameters(config);
+		let bm = CompressStreamBenchmark {
+			cctx: create_counted_cctx(&params),
+			in_chunk: get_size(config, "in_chunk").unwrap_or_else(zstd::cstream_in_size),
+			flush_every: get_size(config, "flush_every"),
+			nb_workers: nb_workers(config),
+			estimated_context_bytes: estimate_cctx_size(&params),
+			out: vec![0; out_chunk],
+		};
+		Box::new(bm)
+	}
+}
+
+impl Benchmark for CompressStreamBenchmark {
+	fn initialize_data_set(&mut self, _data_set: &DataSet) {
+		self.cctx.reset_peak_allocated();
+	}
+
+	fn run_datum(&mut self, datum: &Datum, iters: u64) -> Metrics {
+		let mut compressed_size = 0;
+		let mut timer = Timer::new();
+		for _ in 0..iters {
+			compressed_size += self.run_one(datum, |_| {});
+		}
+		let duration = timer.stop();
+
+		Metrics {
+			uncompressed_size: Some(datum.len() as u64 * iters),
+			compressed_size: Some(compressed_size as u64),
+			duration: Some(duration),
+		}
+	}
+
+	fn verify_data_set(&mut self, data_set: &DataSet) {
+		for datum in data_set.data() {
+			let mut frame = Vec::new();
+			self.run_one(datum, |out| frame.extend_from_slice(out));
+			check_round_trip(data_set, datum, |dst| zstd::decompress(dst, &frame));
+		}
+	}
+
+	fn output_hash(&mut self, data_set: &DataSet) -> Option<u64> {
+		let mut hasher = fasthash::xx::Hasher64::default();
+		for datum in data_set.data() {
+			self.run_one(datum, |out| hasher.write(out));
+		}
+		Some(hasher.finish())
+	}
+
+	fn memory_usage(&self) -> MemoryUsage {
+		MemoryUsage {
+			context_bytes: Some(self.cctx.sizeof() as u64),
+			estimated_context_bytes: self.estimated_context_bytes,
+			peak_allocated_bytes: self.cctx.peak_allocated().map(|peak| peak as u64),
+		}
+	}
+
+	fn update_result(&self, result: &mut BenchmarkResult) {
+		result.nb_workers = self.nb_workers;
+	}
+}
+
+pub struct DecompressStreamBenchmark {
+	cctx: zstd::CCtx,
+	dctx: zstd::DCtx,
+	in_chunk: usize,
+	// Sized to the out_chunk parameter.
+	out: Vec<u8>,
+	c_data: Vec<Vec<u8>>,
+}
+
+impl DecompressStreamBenchmark {
+	fn run_one(&mut self) -> (usize, usize) {
+		let mut output = zstd::OutBuffer {
+			dst: &mut self.out,
+			pos: 0,
+		};
+		let mut d_size = 0;
+		let mut c_size = 0;
+		for frame in &self.c_data {
+			c_size += frame.len();
+			let mut ret = 0;
+			for chunk in frame.chunks(self.in_chunk) {
+				let mut input = zstd::InBuffer { src: chunk, pos: 0 };
+				loop {
+					ret = self.dctx.decompress_stream(&mut output, &mut input);
+					assert!(!zstd::is_error(ret));
+					// A full output buffer may leave data buffered in the DCtx.
+					let flushed = output.pos < output.dst.len();
+					d_size += output.pos;
+					output.pos = 0;
+					if flushed && input.pos == input.src.len() {
+						break;
+					}
+				}
+			}
+			assert_eq!(ret, 0);
+		}
+		(d_size, c_size)
+	}
+}
+
+impl ConfigurableBenchmark for DecompressStreamBenchmark {
+	fn name() -> String {
+		String::from("decompress_stream")
+	}
+
+	fn from_config(config: &BenchmarkConfig) -> Box<dyn Benchmark> {
+		let out_chunk = get_size(config, "out_chunk").unwrap_or_else(zstd::dstream_out_size);
+		let bm = DecompressStreamBenchmark {
+			cctx: create_cctx(&compression_parameters(config)),
+			dctx: zstd::DCtx::with_allocation_counter(),
+			in_chunk: get_size(config, "in_chunk").unwrap_or_else(zstd::dstream_in_size),
+			out: vec![0; out_chunk],
+			c_data: Vec::new(),
+		};
+		Box::new(bm)
+	}
+}
+
+impl Benchmark for DecompressStreamBenchmark {
+	fn initialize_data_set(&mut self, data_set: &DataSet) {
+		self.c_data.clear();
+		for datum in data_set.data() {
+			let mut cdata = vec![0; zstd::compress_bound(datum.len())];
+			let csize = self.cctx.compress(&mut cdata, datum.bytes());
+			assert!(!zstd::is_error(csize));
+			cdata.resize(csize, 0);
+			self.c_data.push(cdata);
+		}
+		self.dctx.reset_peak_allocated();
+	}
+
+	fn run_data_set(&mut self, _data_set: &DataSet, iters: u64) -> Metrics {
+		let mut timer = Timer::new();
+		let mut compressed_size = 0;
+		let mut decompressed_size = 0;
+		for _ in 0..iters {
+			let (d_size, c_size) = self.run_one();
+			decompressed_size += d_size;
+			compressed_size += c_size;
+		}
+		let duration = timer.stop();
+
+		Metrics {
+			uncompressed_size: Some(decompressed_size as u64),
+			compressed_size: Some(compressed_size as u64),
+			duration: Some(duration),
+		}
+	}
+
+	fn memory_usage(&self) -> MemoryUsage {
+		MemoryUsage {
+			context_bytes: Some(self.dctx.sizeof() as u64),
+			estimated_context_bytes: None,
+			peak_allocated_bytes: self.dctx.peak_allocated().map(|peak| peak as u64),
+		}
+	}
+}
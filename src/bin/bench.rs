@@ -108,6 +108,12 @@ fn parse_args() -> Option<BenchArgs> {
 			.value_name("KEY:VALUE")
 			.help("Print the comparison diff between values of KEY against the baseline VALUE. E.g. 'revision:dev'")
 			.takes_value(true))
+		.arg(Arg::with_name("print_significance")
+			.long("print-significance")
+			.value_name("ALPHA")
+			.help("Mark timing diffs significant (*) when Welch's t-test on the run durations gives a p-value below ALPHA, otherwise insignificant (~)")
+			.takes_value(true)
+			.default_value("0.05"))
 		.arg(Arg::with_name("print_commit")
 			.long("print-commit")
 			.hidden(true))
@@ -127,7 +133,9 @@ fn parse_args() -> Option<BenchArgs> {
 	let print_keys = matches.value_of("print_keys").unwrap().split(',').map(|s| s.to_string()).collect();
 	let print_diff = matches.value_of("print_diff").map(|print_diff| {
 		let cmp: Vec<_> = print_diff.split(':').collect();
-		let cmp = Comparison { key: cmp[0].to_string(), baseline: cmp[1].to_string() };
+		let alpha = matches.value_of("print_significance").unwrap();
+		let alpha = alpha.parse().expect("ALPHA must be a number");
+		let cmp = Comparison { key: cmp[0].to_string(), baseline: cmp[1].to_string(), alpha };
 		cmp
 	});
 	let args = BenchArgs {
@@ -198,6 +206,7 @@ fn main_process() {
 				.current_dir(&args.cargo_dir)
 				.env("ZSTD_REPO", args.config.repo())
 				.env("ZSTD_COMMIT", &commit)
+				.env("ZSTD_MULTITHREAD", if args.config.multithread() { "1" } else { "0" })
 				.arg("build")
 				.arg("--release")
 				.arg("--bin")
@@ -272,14 +281,14 @@ fn run_all_benchmarks(args: &BenchArgs) {
 					config_name,
 					data_set.name()
 				);
-				let result = run_benchmark(
+				results.extend(run_benchmark(
 					&args.config,
 					benchmark_name,
 					config_name.as_deref(),
 					&mut *bm,
 					&data_set,
-				);
-				results.push(result);
+					args.config.warmup(bm_config),
+				));
 			}
 		}
 	}
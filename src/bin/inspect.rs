@@ -0,0 +1,204 @@
+extern crate clap;
+use clap::{App, Arg};
+use std::fs;
+use zstd_bench::print::{Format, Row};
+use zstd_bench::zstd;
+
+/// The structure of a compressed block, from the literals and sequences
+/// sections.
+struct CompressedBlock {
+	literals_type: zstd::LiteralsBlockType,
+	c_literals: usize,
+	d_literals: usize,
+	huf_streams: Option<u64>,
+	sequences: u64,
+	repcodes: u64,
+}
+
+/// Huffman compressed literals use a single stream only with the smallest
+/// size format.
+fn huf_streams(c_lits: &[u8], lits_type: &zstd::LiteralsBlockType) -> Option<u64> {
+	match lits_type {
+		zstd::LiteralsBlockType::Compressed | zstd::LiteralsBlockType::Repeat => {
+			if (c_lits[0] >> 2) & 3 == 0 {
+				Some(1)
+			} else {
+				Some(4)
+			}
+		}
+		_ => None,
+	}
+}
+
+/// Reads the number of sequences from the start of a sequences section.
+fn nb_sequences(section: &[u8]) -> u64 {
+	let byte = |i: usize| section[i] as u64;
+	match byte(0) {
+		0..=127 => byte(0),
+		128..=254 => ((byte(0) - 128) << 8) + byte(1),
+		_ => byte(1) + (byte(2) << 8) + 0x7F00,
+	}
+}
+
+fn check(ret: usize) -> usize {
+	if zstd::is_error(ret) {
+		panic!("Corrupt frame: {}", zstd::get_error_name(ret));
+	}
+	ret
+}
+
+fn compressed_blocks(frame: &[u8]) -> Vec<CompressedBlock> {
+	let mut blocks = Vec::new();
+	check(zstd::for_each_literals_block(frame, |c_lits, d_lits, lits_type| {
+		blocks.push(CompressedBlock {
+			huf_streams: huf_streams(c_lits, &lits_type),
+			literals_type: lits_type,
+			c_literals: c_lits.len(),
+			d_literals: d_lits.len(),
+			sequences: 0,
+			repcodes: 0,
+		});
+		zstd::IterationCommand::Continue
+	}));
+	let mut block = blocks.iter_mut();
+	check(zstd::for_each_sequences_block(frame, |section| {
+		block.next().unwrap().sequences = nb_sequences(section);
+		zstd::IterationCommand::Continue
+	}));
+	// Sequences are visited in order, so each block owns the next
+	// `sequences` of them.
+	let mut repcodes = Vec::new();
+	check(zstd::for_each_sequence(frame, |sequence| {
+		repcodes.push(sequence.repcode != 0);
+		zstd::IterationCommand::Continue
+	}));
+	let mut repcodes = repcodes.into_iter();
+	for block in blocks.iter_mut() {
+		let sequences = repcodes.by_ref().take(block.sequences as usize);
+		block.repcodes = sequences.filter(|&repcode| repcode).count() as u64;
+	}
+	blocks
+}
+
+/// Skippable frames start with a magic number in 0x184D2A50..=0x184D2A5F.
+fn is_skippable_frame(frame: &[u8]) -> bool {
+	frame.len() >= 4
+		&& u32::from_le_bytes([frame[0], frame[1], frame[2], frame[3]]) & 0xFFFF_FFF0 == 0x184D_2A50
+}
+
+/// Skippable frames have no blocks, so they're reported as a single row.
+fn inspect_skippable_frame(frame_idx: usize, frame: &[u8], rows: &mut Vec<Row>) {
+	let mut row = Row::default();
+	row.insert_integer("frame", Some(frame_idx as u64));
+	row.insert_integer("block", None);
+	row.insert_string("block_type", Some(String::from("Skippable")));
+	row.insert_integer("block_bytes", Some(frame.len() as u64));
+	row.insert_string("literals_type", None);
+	row.insert_integer("literals_compressed_bytes", None);
+	row.insert_integer("literals_decompressed_bytes", None);
+	row.insert_integer("sequences", None);
+	row.insert_integer("repcodes", None);
+	row.insert_integer("huf_streams", None);
+	rows.push(row);
+}
+
+fn inspect_frame(frame_idx: usize, frame: &[u8], rows: &mut Vec<Row>) {
+	let mut compressed = compressed_blocks(frame).into_iter();
+	let mut block_idx = 0;
+	check(zstd::for_each_block(frame, |block, block_type| {
+		let mut row = Row::default();
+		row.insert_integer("frame", Some(frame_idx as u64));
+		row.insert_integer("block", Some(block_idx));
+		row.insert_string("block_type", Some(format!("{:?}", block_type)));
+		row.insert_integer("block_bytes", Some(block.len() as u64));
+		let block = match block_type {
+			zstd::BlockType::Compressed => compressed.next(),
+			_ => None,
+		};
+		row.insert_string(
+			"literals_type",
+			block.as_ref().map(|b| format!("{:?}", b.literals_type)),
+		);
+		row.insert_integer(
+			"literals_compressed_bytes",
+			block.as_ref().map(|b| b.c_literals as u64),
+		);
+		row.insert_integer(
+			"literals_decompressed_bytes",
+			block.as_ref().map(|b| b.d_literals as u64),
+		);
+		row.insert_integer("sequences", block.as_ref().map(|b| b.sequences));
+		row.insert_integer("repcodes", block.as_ref().map(|b| b.repcodes));
+		row.insert_integer("huf_streams", block.as_ref().and_then(|b| b.huf_streams));
+		rows.push(row);
+		block_idx += 1;
+		zstd::IterationCommand::Continue
+	}));
+}
+
+fn main() {
+	let matches = App::new("Inspect")
+		.about("Prints the block structure of zstd frames")
+		.arg(Arg::with_name("file")
+			.value_name("FILE")
+			.help("A .zst file, or a file to compress with --level")
+			.required(true))
+		.arg(Arg::with_name("level")
+			.short("l")
+			.long("level")
+			.value_name("LEVEL")
+			.help("Compress FILE at this level and inspect the result")
+			.takes_value(true))
+		.arg(Arg::with_name("format")
+			.short("f")
+			.long("format")
+			.value_name("FORMAT")
+			.help("Print format: markdown, pretty, csv, tsv")
+			.takes_value(true)
+			.default_value("pretty"))
+		.get_matches();
+
+	let data = fs::read(matches.value_of("file").unwrap()).unwrap();
+	let data = match matches.value_of("level") {
+		Some(level) => {
+			let level = level.parse().expect("Level must be an integer");
+			let mut cdata = vec![0; zstd::compress_bound(data.len())];
+			let csize = check(zstd::compress(&mut cdata, &data, level));
+			cdata.truncate(csize);
+			cdata
+		}
+		None => data,
+	};
+	let format: Format = matches.value_of("format").unwrap().into();
+
+	let mut rows = Vec::new();
+	let mut src = &data[..];
+	let mut frame_idx = 0;
+	while !src.is_empty() {
+		let frame_size = check(zstd::find_frame_compressed_size(src));
+		if frame_size == 0 {
+			break;
+		}
+		if is_skippable_frame(src) {
+			inspect_skippable_frame(frame_idx, &src[..frame_size], &mut rows);
+		} else {
+			inspect_frame(frame_idx, &src[..frame_size], &mut rows);
+		}
+		src = &src[frame_size..];
+		frame_idx += 1;
+	}
+
+	let keys = [
+		"frame",
+		"block",
+		"block_type",
+		"block_bytes",
+		"literals_type",
+		"literals_compressed_bytes",
+		"literals_decompressed_bytes",
+		"sequences",
+		"repcodes",
+		"huf_streams",
+	];
+	format.print_rows(rows, &keys, None);
+}
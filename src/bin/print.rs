@@ -11,6 +11,10 @@ fn main() {
 		"speed_mbps",
 		"ratio",
 	];
-	let cmp = Comparison { key: "revision".to_string(), baseline: "dev".to_string() };
+	let cmp = Comparison {
+		key: "revision".to_string(),
+		baseline: "dev".to_string(),
+		alpha: 0.05,
+	};
 	Format::Pretty.print_results("results.json", &keys, Some(&cmp));
 }
@@ -68,9 +68,31 @@ impl BenchmarkConfig {
 		self.parameters.get(param)
 	}
 
+	pub fn parameters(&self) -> impl Iterator<Item = (&str, &Parameter)> {
+		self.parameters.iter().map(|(name, param)| (name as &str, param))
+	}
+
 	pub fn get_data_sets(&self) -> &Option<HashSet<String>> {
 		&self.data_sets
 	}
+
+	#[cfg(test)]
+	pub fn with_parameters(parameters: Vec<(&str, Parameter)>) -> Self {
+		BenchmarkConfig {
+			parameters: parameters
+				.into_iter()
+				.map(|(name, param)| (name.to_string(), param))
+				.collect(),
+			data_sets: None,
+		}
+	}
+}
+
+/// Runs made and discarded before measuring, until both limits are reached.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Warmup {
+	pub runs: u64,
+	pub secs: u64,
 }
 
 pub enum DataSetMode {
@@ -120,6 +142,14 @@ pub struct Config {
 	min_runs: Option<u64>,
 	min_ms_per_run: Option<u64>,
 	min_iters_per_run: Option<u64>,
+	multithread: Option<bool>,
+	verify: Option<bool>,
+	output_hash: Option<bool>,
+	perf_counters: Option<bool>,
+	target_precision: Option<f64>,
+	max_secs: Option<u64>,
+	warmup_runs: Option<u64>,
+	warmup_secs: Option<u64>,
 }
 
 fn load_opt_int(dst: &mut Option<u64>, val: Option<&Value>) {
@@ -138,6 +168,14 @@ impl Config {
 			min_runs: None,
 			min_ms_per_run: None,
 			min_iters_per_run: None,
+			multithread: None,
+			verify: None,
+			output_hash: None,
+			perf_counters: None,
+			target_precision: None,
+			max_secs: None,
+			warmup_runs: None,
+			warmup_secs: None,
 		}
 	}
 
@@ -241,6 +279,18 @@ impl Config {
 		load_opt_int(&mut config.min_runs, toml.get("min_runs"));
 		load_opt_int(&mut config.min_ms_per_run, toml.get("min_ms_per_run"));
 		load_opt_int(&mut config.min_iters_per_run, toml.get("min_iters_per_run"));
+		config.multithread = toml.get("multithread").map(|x| x.as_bool().unwrap());
+		config.verify = toml.get("verify").map(|x| x.as_bool().unwrap());
+		config.output_hash = toml.get("output_hash").map(|x| x.as_bool().unwrap());
+		config.perf_counters = toml.get("perf_counters").map(|x| x.as_bool().unwrap());
+		config.target_precision = toml.get("target_precision").map(|x| {
+			let precision = x.as_float().unwrap();
+			assert!(precision > 0., "target_precision must be positive");
+			precision
+		});
+		load_opt_int(&mut config.max_secs, toml.get("max_secs"));
+		load_opt_int(&mut config.warmup_runs, toml.get("warmup_runs"));
+		load_opt_int(&mut config.warmup_secs, toml.get("warmup_secs"));
 
 		config
 	}
@@ -287,4 +337,47 @@ impl Config {
 	pub fn min_ms_per_run(&self) -> u64 {
 		self.min_ms_per_run.unwrap_or(100)
 	}
+
+	pub fn multithread(&self) -> bool {
+		self.multithread.unwrap_or(false)
+	}
+
+	pub fn verify(&self) -> bool {
+		self.verify.unwrap_or(false)
+	}
+
+	pub fn output_hash(&self) -> bool {
+		self.output_hash.unwrap_or(false)
+	}
+
+	pub fn perf_counters(&self) -> bool {
+		self.perf_counters.unwrap_or(false)
+	}
+
+	/// The relative half width of the mean's confidence interval at which to
+	/// stop running. None runs the number of runs fixed by min_secs.
+	pub fn target_precision(&self) -> Option<f64> {
+		self.target_precision
+	}
+
+	/// Caps the runs when target_precision is set.
+	pub fn max_secs(&self) -> u64 {
+		self.max_secs.unwrap_or(60)
+	}
+
+	/// The warmup for a benchmark config, whose warmup_runs and warmup_secs
+	/// override the global ones.
+	pub fn warmup(&self, bm_config: &BenchmarkConfig) -> Warmup {
+		let get = |name, global: Option<u64>| {
+			bm_config
+				.get_parameter(name)
+				.map(|v| v.unwrap_integer() as u64)
+				.or(global)
+				.unwrap_or(0)
+		};
+		Warmup {
+			runs: get("warmup_runs", self.warmup_runs),
+			secs: get("warmup_secs", self.warmup_secs),
+		}
+	}
 }
@@ -2,7 +2,9 @@ pub mod benchmark;
 pub mod config;
 pub mod benchmarks;
 pub mod zstd;
+pub mod perf;
 pub mod print;
+pub mod stats;
 
 #[cfg(test)]
 mod tests {
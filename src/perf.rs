@@ -0,0 +1,256 @@
+extern crate libc;
+
+/// A hardware event counted with perf_event_open().
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Counter {
+	Cycles,
+	Instructions,
+	Branches,
+	BranchMisses,
+	L1dMisses,
+	LlcMisses,
+}
+
+pub const COUNTERS: [Counter; 6] = [
+	Counter::Cycles,
+	Counter::Instructions,
+	Counter::Branches,
+	Counter::BranchMisses,
+	Counter::L1dMisses,
+	Counter::LlcMisses,
+];
+
+const PERF_TYPE_HARDWARE: u32 = 0;
+const PERF_TYPE_HW_CACHE: u32 = 3;
+
+const PERF_COUNT_HW_CPU_CYCLES: u64 = 0;
+const PERF_COUNT_HW_INSTRUCTIONS: u64 = 1;
+const PERF_COUNT_HW_BRANCH_INSTRUCTIONS: u64 = 4;
+const PERF_COUNT_HW_BRANCH_MISSES: u64 = 5;
+
+const PERF_COUNT_HW_CACHE_L1D: u64 = 0;
+const PERF_COUNT_HW_CACHE_LL: u64 = 2;
+const PERF_COUNT_HW_CACHE_OP_READ: u64 = 0;
+const PERF_COUNT_HW_CACHE_RESULT_MISS: u64 = 1;
+
+impl Counter {
+	pub fn name(&self) -> &'static str {
+		match self {
+			Counter::Cycles => "cycles",
+			Counter::Instructions => "instructions",
+			Counter::Branches => "branches",
+			Counter::BranchMisses => "branch_misses",
+			Counter::L1dMisses => "l1d_misses",
+			Counter::LlcMisses => "llc_misses",
+		}
+	}
+
+	fn event(&self) -> (u32, u64) {
+		let cache_read_misses = |cache| {
+			cache | (PERF_COUNT_HW_CACHE_OP_READ << 8) | (PERF_COUNT_HW_CACHE_RESULT_MISS << 16)
+		};
+		match self {
+			Counter::Cycles => (PERF_TYPE_HARDWARE, PERF_COUNT_HW_CPU_CYCLES),
+			Counter::Instructions => (PERF_TYPE_HARDWARE, PERF_COUNT_HW_INSTRUCTIONS),
+			Counter::Branches => (PERF_TYPE_HARDWARE, PERF_COUNT_HW_BRANCH_INSTRUCTIONS),
+			Counter::BranchMisses => (PERF_TYPE_HARDWARE, PERF_COUNT_HW_BRANCH_MISSES),
+			Counter::L1dMisses => (PERF_TYPE_HW_CACHE, cache_read_misses(PERF_COUNT_HW_CACHE_L1D)),
+			Counter::LlcMisses => (PERF_TYPE_HW_CACHE, cache_read_misses(PERF_COUNT_HW_CACHE_LL)),
+		}
+	}
+}
+
+/// The first version of perf_event_attr, which every kernel accepts.
+#[repr(C)]
+#[derive(Default)]
+struct PerfEventAttr {
+	type_: u32,
+	size: u32,
+	config: u64,
+	sample_period: u64,
+	sample_type: u64,
+	read_format: u64,
+	flags: u64,
+	wakeup_events: u32,
+	bp_type: u32,
+	config1: u64,
+}
+
+const PERF_FORMAT_TOTAL_TIME_ENABLED: u64 = 1 << 0;
+const PERF_FORMAT_TOTAL_TIME_RUNNING: u64 = 1 << 1;
+const PERF_FORMAT_GROUP: u64 = 1 << 3;
+
+const ATTR_FLAG_DISABLED: u64 = 1 << 0;
+const ATTR_FLAG_EXCLUDE_KERNEL: u64 = 1 << 5;
+const ATTR_FLAG_EXCLUDE_HV: u64 = 1 << 6;
+
+#[cfg(target_os = "linux")]
+mod sys {
+	use super::PerfEventAttr;
+
+	// _IO('$', n)
+	pub const PERF_EVENT_IOC_ENABLE: libc::c_ulong = 0x2400;
+	pub const PERF_EVENT_IOC_DISABLE: libc::c_ulong = 0x2401;
+	pub const PERF_EVENT_IOC_RESET: libc::c_ulong = 0x2403;
+	pub const PERF_IOC_FLAG_GROUP: libc::c_ulong = 1;
+
+	/// Opens a counter for the calling thread on any CPU, in the group led by
+	/// `group_fd` unless it is -1. Returns -1 on failure.
+	pub fn perf_event_open(attr: &PerfEventAttr, group_fd: i32) -> i32 {
+		let fd = unsafe {
+			libc::syscall(
+				libc::SYS_perf_event_open,
+				attr as *const PerfEventAttr,
+				0,
+				-1,
+				group_fd,
+				0,
+			)
+		};
+		fd as i32
+	}
+
+	pub fn ioctl(fd: i32, request: libc::c_ulong, arg: libc::c_ulong) {
+		let ret = unsafe { libc::ioctl(fd, request as _, arg) };
+		assert_eq!(ret, 0, "perf event ioctl failed");
+	}
+
+	pub fn read(fd: i32, values: &mut [u64]) {
+		let size = std::mem::size_of_val(values);
+		let ret = unsafe { libc::read(fd, values.as_mut_ptr() as *mut libc::c_void, size) };
+		assert_eq!(ret, size as isize, "perf event read failed");
+	}
+
+	pub fn close(fd: i32) {
+		unsafe {
+			libc::close(fd);
+		}
+	}
+}
+
+#[cfg(not(target_os = "linux"))]
+mod sys {
+	use super::PerfEventAttr;
+
+	pub const PERF_EVENT_IOC_ENABLE: u64 = 0;
+	pub const PERF_EVENT_IOC_DISABLE: u64 = 0;
+	pub const PERF_EVENT_IOC_RESET: u64 = 0;
+	pub const PERF_IOC_FLAG_GROUP: u64 = 0;
+
+	pub fn perf_event_open(_attr: &PerfEventAttr, _group_fd: i32) -> i32 {
+		-1
+	}
+
+	pub fn ioctl(_fd: i32, _request: u64, _arg: u64) {}
+
+	pub fn read(_fd: i32, _values: &mut [u64]) {}
+
+	pub fn close(_fd: i32) {}
+}
+
+/// Counts hardware events of the calling thread in user space. Threads
+/// created by zstd, like multithreaded compression's workers, aren't
+/// counted.
+///
+/// The counters are opened as one group, so they are scheduled onto the PMU
+/// together and count over the same time. If the kernel still multiplexes
+/// the group, the counts are scaled up to the time it was enabled.
+pub struct Counters {
+	// The group leader comes first.
+	events: Vec<(Counter, i32)>,
+	warned_unscheduled: bool,
+}
+
+impl Counters {
+	/// Opens every counter the machine supports and perf_event_paranoid
+	/// allows, skipping the others. The group is led by cycles, or by the
+	/// first counter that opens when cycles isn't available.
+	pub fn open() -> Self {
+		let mut events: Vec<(Counter, i32)> = Vec::new();
+		for counter in COUNTERS.iter() {
+			let (type_, config) = counter.event();
+			let leader = events.first().map(|(_, fd)| *fd);
+			// Members follow the leader, so only it starts disabled.
+			let flags = ATTR_FLAG_EXCLUDE_KERNEL | ATTR_FLAG_EXCLUDE_HV;
+			let attr = PerfEventAttr {
+				type_,
+				size: std::mem::size_of::<PerfEventAttr>() as u32,
+				config,
+				read_format: PERF_FORMAT_GROUP
+					| PERF_FORMAT_TOTAL_TIME_ENABLED
+					| PERF_FORMAT_TOTAL_TIME_RUNNING,
+				flags: if leader.is_none() { flags | ATTR_FLAG_DISABLED } else { flags },
+				..PerfEventAttr::default()
+			};
+			let fd = sys::perf_event_open(&attr, leader.unwrap_or(-1));
+			if fd >= 0 {
+				events.push((*counter, fd));
+			}
+		}
+		Counters {
+			events,
+			warned_unscheduled: false,
+		}
+	}
+
+	pub fn counters(&self) -> Vec<Counter> {
+		self.events.iter().map(|(counter, _)| *counter).collect()
+	}
+
+	fn leader(&self) -> Option<i32> {
+		self.events.first().map(|(_, fd)| *fd)
+	}
+
+	/// Zeroes and starts the counters.
+	pub fn start(&mut self) {
+		if let Some(leader) = self.leader() {
+			sys::ioctl(leader, sys::PERF_EVENT_IOC_RESET, sys::PERF_IOC_FLAG_GROUP);
+			sys::ioctl(leader, sys::PERF_EVENT_IOC_ENABLE, sys::PERF_IOC_FLAG_GROUP);
+		}
+	}
+
+	/// Stops the counters, returning the counts since start(), in the order
+	/// of counters(). None if the group never got onto the PMU, since its
+	/// zero counts would look like an idle run.
+	pub fn stop(&mut self) -> Option<Vec<u64>> {
+		let leader = match self.leader() {
+			Some(leader) => leader,
+			None => return Some(Vec::new()),
+		};
+		sys::ioctl(leader, sys::PERF_EVENT_IOC_DISABLE, sys::PERF_IOC_FLAG_GROUP);
+		// The group is read as nr, time_enabled, time_running, then a value
+		// per counter.
+		let mut values = vec![0u64; 3 + self.events.len()];
+		sys::read(leader, &mut values);
+		let (enabled, running) = (values[1], values[2]);
+		if running == 0 {
+			if !self.warned_unscheduled {
+				println!(
+					"perf counters were never scheduled, {} counters may not fit the PMU",
+					self.events.len()
+				);
+				self.warned_unscheduled = true;
+			}
+			return None;
+		}
+		let counts = values[3..]
+			.iter()
+			.map(|&count| {
+				if running >= enabled {
+					count
+				} else {
+					(count as f64 * enabled as f64 / running as f64) as u64
+				}
+			})
+			.collect();
+		Some(counts)
+	}
+}
+
+impl Drop for Counters {
+	fn drop(&mut self) {
+		for (_, fd) in &self.events {
+			sys::close(*fd);
+		}
+	}
+}
@@ -1,6 +1,8 @@
 extern crate itertools;
 extern crate serde_json;
-use crate::benchmark::BenchmarkResult;
+use crate::benchmark::{BenchmarkResult, Statistic};
+use crate::perf::COUNTERS;
+use crate::stats::welch_t_test;
 use itertools::Itertools;
 use std::cmp::Ordering;
 use std::collections::HashMap;
@@ -10,6 +12,9 @@ use std::path::Path;
 pub struct Comparison {
 	pub key: String,
 	pub baseline: String,
+	/// Timing diffs with a Welch's t-test p-value below this are marked
+	/// significant.
+	pub alpha: f64,
 }
 
 #[derive(PartialEq, Eq, Clone, Copy)]
@@ -172,7 +177,14 @@ impl Format {
 		}
 	}
 
-	fn add_rows(&self, last: bool, rows: &[Row], key: &str, lines: &mut [String]) {
+	fn add_rows(
+		&self,
+		last: bool,
+		rows: &[Row],
+		key: &str,
+		alpha: Option<f64>,
+		lines: &mut [String],
+	) {
 		let value0 = rows[0].get(key);
 		let is_comparison = value0.is_comparison();
 		let title = rows[0].title(key);
@@ -213,9 +225,16 @@ impl Format {
 					let title = format!("{} ({} - {})", title, c, baseline);
 					let values: Vec<_> = rows
 						.iter()
-						.map(|r| r.get(key).unwrap_comparison())
-						.map(|c| diff(&c[0].1, &c[i].1))
-						.map(|d| format!("{:.1}%", d * 100.0))
+						.map(|r| {
+							let c = r.get(key).unwrap_comparison();
+							let d = diff(&c[0].1, &c[i].1);
+							let mark = alpha
+								.filter(|_| is_timing(key))
+								.and_then(|alpha| r.significant(i, alpha))
+								.map(|significant| if significant { " *" } else { " ~" })
+								.unwrap_or("");
+							format!("{:.1}%{}", d * 100.0, mark)
+						})
 						.collect();
 					self.add_values(last, &title, pad, &values, lines);
 				}
@@ -254,7 +273,7 @@ impl Format {
 					continue;
 				}
 			}
-			self.add_rows(last, &rows, key, &mut lines);
+			self.add_rows(last, &rows, key, cmp.map(|c| c.alpha), &mut lines);
 		}
 		for i in 0..nlines {
 			let sub_header = i == sub_header_line && self.has_sub_header();
@@ -371,10 +390,20 @@ impl<T: Into<Value>> From<Option<T>> for Value {
 	}
 }
 
-#[derive(Clone)]
+#[derive(Clone, Default)]
 pub struct Row {
 	values: HashMap<String, Value>,
 	titles: HashMap<&'static str, &'static str>,
+	// The duration per iteration of each run of the result, or of each
+	// compared result with the baseline first. Per iteration, since each
+	// result calibrates its own iterations per run.
+	samples: Vec<Vec<f64>>,
+}
+
+/// Whether the key is measured from the run durations, so its diffs can be
+/// tested against the noise in the samples.
+fn is_timing(key: &str) -> bool {
+	key.starts_with("duration_ns") || key.starts_with("speed_mbps") || key == "ns_per_iter"
 }
 
 impl Row {
@@ -402,14 +431,25 @@ impl Row {
 		self.values.get(key).unwrap()
 	}
 
+	/// Whether the compared result `i` differs significantly from the
+	/// baseline, None without enough samples.
+	fn significant(&self, i: usize, alpha: f64) -> Option<bool> {
+		let p = welch_t_test(self.samples.first()?, self.samples.get(i)?)?;
+		Some(p < alpha)
+	}
+
 	fn is_result(&self, key: &str) -> bool {
-		if key == "ratio" {
+		let derived_counters = ["cycles_per_byte", "ipc", "branch_miss_rate"];
+		if derived_counters.contains(&key) || COUNTERS.iter().any(|c| c.name() == key) {
+			return true;
+		}
+		if key.ends_with("ratio") {
 			true
 		} else if key.ends_with("bytes") {
 			true
 		} else if key.starts_with("duration_ns") {
 			true
-		} else if key.starts_with("speed_mbps") {
+		} else if key.starts_with("speed_mbps") || key == "ns_per_iter" {
 			true
 		} else {
 			false
@@ -419,6 +459,17 @@ impl Row {
 	pub fn keys(&self) -> impl Iterator<Item = &String> {
 		self.values.keys()
 	}
+
+	/// Sets a string value, so rows can be built for things other than
+	/// benchmark results. None is displayed as N/A.
+	pub fn insert_string(&mut self, key: &str, value: Option<String>) {
+		self.values.insert(key.to_string(), value.into());
+	}
+
+	/// Sets an integer value. None is displayed as N/A.
+	pub fn insert_integer(&mut self, key: &str, value: Option<u64>) {
+		self.values.insert(key.to_string(), value.into());
+	}
 }
 
 fn sort_rows<S: AsRef<str>>(mut rows: Vec<Row>, order: &[S]) -> Vec<Row> {
@@ -460,6 +511,15 @@ fn compare_rows<S: AsRef<str>>(rows: Vec<Row>, keys: &[S], cmp: &Comparison) ->
 		let cmp_rows: Vec<_> = cmp_iter.collect();
 		assert_ne!(cmp_rows.len(), 0);
 		let mut out_row = cmp_rows[0].clone();
+		out_row.samples.clear();
+		for r in &cmp_rows {
+			let samples = r.samples.first().cloned().unwrap_or_default();
+			if r.get(&cmp.key).unwrap_str() == cmp.baseline {
+				out_row.samples.insert(0, samples);
+			} else {
+				out_row.samples.push(samples);
+			}
+		}
 		for key in &suffix {
 			let mut cmp_value = Vec::new();
 			for r in &cmp_rows {
@@ -492,7 +552,19 @@ fn is_null_comparison(rows: &[Row], key: &str) -> bool {
 
 impl From<BenchmarkResult> for Row {
 	fn from(result: BenchmarkResult) -> Self {
+		let iters_per_run = result.iters_per_run as f64;
+		let samples = vec![result
+			.duration_ns_samples
+			.iter()
+			.map(|&duration| duration as f64 / iters_per_run)
+			.collect()];
 		let mut values: HashMap<_, Value> = HashMap::new();
+		// Per iteration, so results that calibrated different iterations per
+		// run can be compared.
+		for counter in &COUNTERS {
+			let mean = result.counter(*counter).map(|s| s.mean as f64 / iters_per_run);
+			values.insert(counter.name(), mean.into());
+		}
 		values.insert("commit", result.zstd_commit[..10].to_string().into());
 		values.insert("revision", result.zstd_revision.into());
 		values.insert("tag", result.zstd_tag.into());
@@ -508,8 +580,15 @@ impl From<BenchmarkResult> for Row {
 		values.insert("benchmark", result.benchmark_name.into());
 		values.insert("config", result.config_name.into());
 		values.insert("dataset", result.data_set.into());
+		values.insert("bucket", result.bucket.into());
 		values.insert("iters_per_run", result.iters_per_run.into());
 		values.insert("runs", result.runs.into());
+		values.insert("warmup_runs", result.warmup_runs.into());
+		values.insert("warmup_ns", result.warmup_ns.into());
+		values.insert(
+			"precision_reached",
+			result.precision_reached.map(|r| r.to_string()).into(),
+		);
 		values.insert("uncompressed_bytes", result.uncompressed_bytes.into());
 		values.insert("compressed_bytes", result.compressed_bytes.into());
 		values.insert("duration_ns", result.duration_ns.mean.into());
@@ -517,6 +596,41 @@ impl From<BenchmarkResult> for Row {
 		values.insert("duration_ns_max", result.duration_ns.max.into());
 		values.insert("duration_ns_median", result.duration_ns.median.into());
 		values.insert("duration_ns_stddev", result.duration_ns.std_dev.into());
+		values.insert("duration_ns_mad", result.duration_ns.mad.into());
+		values.insert(
+			"duration_ns_trimmed_mean",
+			result.duration_ns.trimmed_mean.into(),
+		);
+		values.insert("duration_ns_ci_low", result.duration_ns.ci_low.into());
+		values.insert("duration_ns_ci_high", result.duration_ns.ci_high.into());
+		values.insert("nb_workers", result.nb_workers.into());
+		values.insert(
+			"reuse_context",
+			result.reuse_context.map(|r| r.to_string()).into(),
+		);
+		values.insert("context_bytes", result.context_bytes.into());
+		values.insert(
+			"estimated_context_bytes",
+			result.estimated_context_bytes.into(),
+		);
+		values.insert("peak_allocated_bytes", result.peak_allocated_bytes.into());
+		values.insert(
+			"output_hash",
+			result.output_hash.map(|h| format!("{:016x}", h)).into(),
+		);
+		values.insert(
+			"dictionary_hash",
+			result.dictionary_hash.map(|h| format!("{:016x}", h)).into(),
+		);
+		values.insert("dictionary_size", result.dictionary_size.into());
+		values.insert(
+			"holdout_uncompressed_bytes",
+			result.holdout_uncompressed_bytes.into(),
+		);
+		values.insert(
+			"holdout_compressed_bytes",
+			result.holdout_compressed_bytes.into(),
+		);
 
 		let uncompressed_bytes = result.uncompressed_bytes.clone();
 		let compressed_bytes = result.compressed_bytes.clone();
@@ -527,7 +641,32 @@ impl From<BenchmarkResult> for Row {
 			_ => None,
 		};
 
+		let holdout_ratio = match (
+			result.holdout_uncompressed_bytes,
+			result.holdout_compressed_bytes,
+		) {
+			(Some(u), Some(c)) => Some((u as f64) / (c as f64)),
+			_ => None,
+		};
+
+		let cycles = result.cycles.as_ref().map(|s| s.mean as f64);
+		let per = |x: Option<f64>, y: Option<f64>| match (x, y) {
+			(Some(x), Some(y)) => Some(x / y),
+			_ => None,
+		};
+		let mean = |s: &Option<Statistic>| s.as_ref().map(|s| s.mean as f64);
+		values.insert(
+			"cycles_per_byte",
+			per(cycles, uncompressed_bytes.map(|u| u as f64)).into(),
+		);
+		values.insert("ipc", per(mean(&result.instructions), cycles).into());
+		values.insert(
+			"branch_miss_rate",
+			per(mean(&result.branch_misses), mean(&result.branches)).into(),
+		);
+
 		values.insert("ratio", ratio.into());
+		values.insert("holdout_ratio", holdout_ratio.into());
 		values.insert("speed_mbps", speed_mbps(result.duration_ns.mean).into());
 		values.insert("speed_mbps_min", speed_mbps(result.duration_ns.max).into());
 		values.insert("speed_mbps_max", speed_mbps(result.duration_ns.min).into());
@@ -535,10 +674,25 @@ impl From<BenchmarkResult> for Row {
 			"speed_mbps_median",
 			speed_mbps(result.duration_ns.median).into(),
 		);
-		// TODO: This is probably wrong...
+		// Speed is inversely proportional to duration, so its standard
+		// deviation scales by speed / duration to first order.
+		let duration = &result.duration_ns;
+		let speed_std_dev = speed_mbps(duration.mean)
+			.map(|speed| speed * (duration.std_dev as f64) / (duration.mean as f64));
+		values.insert("speed_mbps_stddev", speed_std_dev.into());
+		// The half width of the confidence interval, which isn't symmetric
+		// for the speed. Unbounded when the duration's lower bound reaches 0.
+		let speed_ci = match (speed_mbps(duration.ci_low), speed_mbps(duration.ci_high)) {
+			(Some(high), Some(low)) if duration.ci_low > 0 => Some((high - low) / 2.),
+			_ => None,
+		};
+		values.insert("speed_mbps_ci", speed_ci.into());
 		values.insert(
-			"speed_mbps_stddev",
-			speed_mbps(result.duration_ns.std_dev).into(),
+			"speed_mbps_pm",
+			speed_mbps(duration.mean)
+				.zip(speed_ci)
+				.map(|(speed, ci)| format!("{:.2} +/- {:.2}", speed, ci))
+				.into(),
 		);
 
 		values.insert(
@@ -552,12 +706,17 @@ impl From<BenchmarkResult> for Row {
 		titles.insert("cc_version", "Compiler Version");
 		titles.insert("cflags", "Compiler Flags");
 		titles.insert("ns_per_iter", "ns / iter");
+		titles.insert("speed_mbps_pm", "Speed MB/s +/- 95% CI");
 
 		let values = values
 			.into_iter()
 			.map(|(k, v)| (k.to_string(), v))
 			.collect();
 
-		Row { values, titles }
+		Row {
+			values,
+			titles,
+			samples,
+		}
 	}
 }
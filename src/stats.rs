@@ -0,0 +1,117 @@
+/// The natural log of the gamma function, by the Lanczos approximation, for
+/// x >= 0.5.
+fn ln_gamma(x: f64) -> f64 {
+	const G: f64 = 7.;
+	const COEFFICIENTS: [f64; 9] = [
+		0.999_999_999_999_809_9,
+		676.520_368_121_885_1,
+		-1_259.139_216_722_402_8,
+		771.323_428_777_653_1,
+		-176.615_029_162_140_6,
+		12.507_343_278_686_905,
+		-0.138_571_095_265_720_12,
+		9.984_369_578_019_572e-6,
+		1.505_632_735_149_311_6e-7,
+	];
+	let x = x - 1.;
+	let t = x + G + 0.5;
+	let sum = COEFFICIENTS
+		.iter()
+		.enumerate()
+		.skip(1)
+		.fold(COEFFICIENTS[0], |sum, (i, c)| sum + c / (x + i as f64));
+	0.5 * (2. * std::f64::consts::PI).ln() + (x + 0.5) * t.ln() - t + sum.ln()
+}
+
+/// The continued fraction of the incomplete beta function, evaluated with
+/// Lentz's method.
+fn beta_continued_fraction(a: f64, b: f64, x: f64) -> f64 {
+	const EPSILON: f64 = 1e-12;
+	const TINY: f64 = 1e-300;
+	let clamp = |v: f64| if v.abs() < TINY { TINY } else { v };
+
+	let mut c = 1.;
+	let mut d = 1. / clamp(1. - (a + b) * x / (a + 1.));
+	let mut h = d;
+	for m in 1..=200 {
+		let m = m as f64;
+		let even = m * (b - m) * x / ((a + 2. * m - 1.) * (a + 2. * m));
+		d = 1. / clamp(1. + even * d);
+		c = clamp(1. + even / c);
+		h *= d * c;
+		let odd = -(a + m) * (a + b + m) * x / ((a + 2. * m) * (a + 2. * m + 1.));
+		d = 1. / clamp(1. + odd * d);
+		c = clamp(1. + odd / c);
+		let delta = d * c;
+		h *= delta;
+		if (delta - 1.).abs() < EPSILON {
+			break;
+		}
+	}
+	h
+}
+
+/// The regularized incomplete beta function I_x(a, b).
+fn incomplete_beta(a: f64, b: f64, x: f64) -> f64 {
+	if x <= 0. {
+		return 0.;
+	}
+	if x >= 1. {
+		return 1.;
+	}
+	let front =
+		(ln_gamma(a + b) - ln_gamma(a) - ln_gamma(b) + a * x.ln() + b * (1. - x).ln()).exp();
+	// The continued fraction converges quickly on this side of the mean.
+	if x < (a + 1.) / (a + b + 2.) {
+		front * beta_continued_fraction(a, b, x) / a
+	} else {
+		1. - front * beta_continued_fraction(b, a, 1. - x) / b
+	}
+}
+
+/// The two sided p-value of `t` in Student's t-distribution with `df`
+/// degrees of freedom.
+fn t_test_p_value(t: f64, df: f64) -> f64 {
+	incomplete_beta(df / 2., 0.5, df / (df + t * t))
+}
+
+fn mean_and_variance(data: &[f64]) -> (f64, f64) {
+	let n = data.len() as f64;
+	let mean = data.iter().sum::<f64>() / n;
+	let variance = data.iter().map(|&x| (x - mean).powi(2)).sum::<f64>() / (n - 1.);
+	(mean, variance)
+}
+
+/// Welch's t-test of whether the samples have the same mean, without
+/// assuming they have the same variance. Returns the two sided p-value, or
+/// None if either has fewer than two samples.
+pub fn welch_t_test(x: &[f64], y: &[f64]) -> Option<f64> {
+	if x.len() < 2 || y.len() < 2 {
+		return None;
+	}
+	let (x_mean, x_var) = mean_and_variance(x);
+	let (y_mean, y_var) = mean_and_variance(y);
+	let x_err = x_var / (x.len() as f64);
+	let y_err = y_var / (y.len() as f64);
+	let err = x_err + y_err;
+	if err == 0. {
+		return Some(if x_mean == y_mean { 1. } else { 0. });
+	}
+	let t = (x_mean - y_mean) / err.sqrt();
+	// The Welch-Satterthwaite approximation of the degrees of freedom.
+	let df = err * err
+		/ (x_err * x_err / (x.len() as f64 - 1.) + y_err * y_err / (y.len() as f64 - 1.));
+	Some(t_test_p_value(t, df))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn t_test_p_value_matches_tables() {
+		assert!((t_test_p_value(2.228, 10.) - 0.05).abs() < 1e-3);
+		assert!((t_test_p_value(12.706, 1.) - 0.05).abs() < 1e-3);
+		assert!((t_test_p_value(0., 5.) - 1.).abs() < 1e-9);
+	}
+}
@@ -3,6 +3,7 @@
 #include <stdlib.h>
 
 #include "common/cpu.h"
+#include "common/fse.h"
 #include "common/huf.h"
 #include "common/zstd_internal.h"
 #include "compress/zstd_compress_internal.h"
@@ -11,6 +12,16 @@
 #include "decompress/zstd_decompress_internal.h"
 #include "zstd.h"
 
+// Included rather than linked so the sequences benchmark can call zstd's
+// static sequence decoder. build.rs doesn't compile it separately. Revisions
+// that moved it only lose ZSTD_decodeSequences().
+#if defined(__has_include)
+#if __has_include("decompress/zstd_decompress_block.c")
+#define ZSTD_BENCH_DECOMPRESS_BLOCK_C
+#include "decompress/zstd_decompress_block.c"
+#endif
+#endif
+
 #define PANIC() abort()
 #define CONTROL(x)                                                             \
   do {                                                                         \
@@ -58,10 +69,68 @@ size_t ZSTD_forEachLiteralsBlock(void const *src, size_t srcSize,
 
 size_t ZSTD_decodeAllLiteralBlocks(void const *src, size_t srcSize);
 
+size_t ZSTD_getSequencesFromBlock(void const **sequences, void const *src,
+                                  size_t srcSize);
+
+typedef enum {
+  ZSTD_FSETableType_literalLengths = 0,
+  ZSTD_FSETableType_offsets = 1,
+  ZSTD_FSETableType_matchLengths = 2,
+} ZSTD_FSETableType_e;
+
+typedef int (*ZSTD_FSETableCallback_t)(void *opaque, void const *nCount,
+                                       size_t nCountSize,
+                                       ZSTD_FSETableType_e type);
+
+size_t ZSTD_forEachFSETable(void const *src, size_t srcSize,
+                            ZSTD_FSETableCallback_t callback, void *opaque);
+
+size_t ZSTD_FSE_sizeofCTableU32(unsigned maxSymbolValue, unsigned tableLog);
+size_t ZSTD_FSE_sizeofDTableU32(unsigned tableLog);
+size_t ZSTD_FSE_sizeofWorkspaceU32(unsigned maxSymbolValue, unsigned tableLog);
+size_t ZSTD_FSE_readNCount(short *normalizedCounter, unsigned *maxSymbolValue,
+                           unsigned *tableLog, void const *src, size_t srcSize);
+size_t ZSTD_FSE_buildCTable(void *ctable, short const *normalizedCounter,
+                            unsigned maxSymbolValue, unsigned tableLog,
+                            void *workspace, size_t workspaceSize);
+size_t ZSTD_FSE_buildDTable(void *dtable, short const *normalizedCounter,
+                            unsigned maxSymbolValue, unsigned tableLog,
+                            void *workspace, size_t workspaceSize);
+
+typedef int (*ZSTD_SequencesBlockCallback_t)(void *opaque,
+                                             void const *sequences,
+                                             size_t sequencesSize);
+
+size_t ZSTD_forEachSequencesBlock(void const *src, size_t srcSize,
+                                  ZSTD_SequencesBlockCallback_t callback,
+                                  void *opaque);
+
+size_t ZSTD_decodeSequences(ZSTD_DCtx *dctx, void const *src, size_t srcSize);
+
+typedef struct {
+  uint32_t litLength;
+  uint32_t matchLength;
+  // The resolved offset, including for repcodes.
+  uint32_t offset;
+  // The offset value 1-3 if this is a repcode, otherwise 0. The repcode it
+  // refers to depends on whether litLength is 0.
+  uint32_t repcode;
+} ZSTD_DecodedSequence;
+
+typedef int (*ZSTD_SequenceCallback_t)(void *opaque,
+                                       ZSTD_DecodedSequence const *sequence);
+
+size_t ZSTD_forEachSequence(void const *src, size_t srcSize,
+                            ZSTD_SequenceCallback_t callback, void *opaque);
+
+size_t ZSTD_compressBeginForSize(ZSTD_CCtx *cctx, int level, size_t srcSize);
+
 void *ZSTD_CompressLiteralsBlockContext_create(void);
 void ZSTD_CompressLiteralsBlockContext_free(void *ctx);
+void ZSTD_CompressLiteralsBlockContext_reset(void *ctx);
 size_t ZSTD_compressLiteralsBlock(void *ctx, void const *src, size_t srcSize,
-                                  int suspectUncompressible);
+                                  int strategy, int suspectUncompressible,
+                                  int carryTables);
 
 static ZSTD_BlockType_e ZSTD_BlockType_map(blockType_e type) {
   switch (type) {
@@ -251,8 +320,10 @@ size_t ZSTD_forEachLiteralsBlock(void const *src, size_t srcSize,
 }
 
 typedef struct {
-  ZSTD_hufCTables_t prev;
-  ZSTD_hufCTables_t next;
+  // The previous and next tables, swapped after each block when carrying
+  // tables like the block compressor does.
+  ZSTD_hufCTables_t tables[2];
+  int prev;
   uint8_t dst[128 * 1024];
   uint64_t workspace[2048];
   int bmi2;
@@ -264,30 +335,46 @@ void *ZSTD_CompressLiteralsBlockContext_create(void) {
           sizeof(ZSTD_CompressLiteralsBlockContext));
   if (ctx == NULL)
     return ctx;
-  ctx->prev.repeatMode = HUF_repeat_none;
+  ZSTD_CompressLiteralsBlockContext_reset(ctx);
   ctx->bmi2 = ZSTD_cpuid_bmi2(ZSTD_cpuid());
   return ctx;
 }
 
 void ZSTD_CompressLiteralsBlockContext_free(void *ctx) { free(ctx); }
 
+// Forgets the previous table, like the start of a new frame.
+void ZSTD_CompressLiteralsBlockContext_reset(void *opaque) {
+  ZSTD_CompressLiteralsBlockContext *ctx =
+      (ZSTD_CompressLiteralsBlockContext *)opaque;
+  ctx->prev = 0;
+  ctx->tables[0].repeatMode = HUF_repeat_none;
+}
+
 size_t ZSTD_compressLiteralsBlock(void *opaque, void const *src, size_t srcSize,
-                                  int suspectUncompressible) {
+                                  int strategy, int suspectUncompressible,
+                                  int carryTables) {
   ZSTD_CompressLiteralsBlockContext *ctx =
       (ZSTD_CompressLiteralsBlockContext *)opaque;
   RETURN_ERROR_IF(srcSize > sizeof(ctx->dst), srcSize_wrong, "too many lits");
+  ZSTD_hufCTables_t const *prev = &ctx->tables[ctx->prev];
+  ZSTD_hufCTables_t *next = &ctx->tables[ctx->prev ^ 1];
 #if ZSTD_VERSION_NUMBER >= 10500
-  return ZSTD_compressLiterals(
-      &ctx->prev, &ctx->next, ZSTD_fast, /* disableLiteralCompression */ 0,
+  size_t const cSize = ZSTD_compressLiterals(
+      prev, next, (ZSTD_strategy)strategy, /* disableLiteralCompression */ 0,
       ctx->dst, sizeof(ctx->dst), src, srcSize, ctx->workspace,
       sizeof(ctx->workspace), ctx->bmi2, suspectUncompressible);
 #else
   (void)suspectUncompressible;
-  return ZSTD_compressLiterals(&ctx->prev, &ctx->next, ZSTD_fast,
-                               /* disableLiteralCompression */ 0, ctx->dst,
-                               sizeof(ctx->dst), src, srcSize, ctx->workspace,
-                               sizeof(ctx->workspace), ctx->bmi2);
+  size_t const cSize = ZSTD_compressLiterals(
+      prev, next, (ZSTD_strategy)strategy,
+      /* disableLiteralCompression */ 0, ctx->dst, sizeof(ctx->dst), src,
+      srcSize, ctx->workspace, sizeof(ctx->workspace), ctx->bmi2);
 #endif
+  FORWARD_IF_ERROR(cSize, "compress literals failed");
+  // The next block may repeat or check against this block's table.
+  if (carryTables)
+    ctx->prev ^= 1;
+  return cSize;
 }
 
 size_t ZSTD_decodeLiteralsBlock(ZSTD_DCtx* dctx, const void* src, size_t srcSize);
@@ -310,3 +397,417 @@ size_t HUF_sizeofWorkspaceU32() {
 int ZSTD_hasBMI2() {
   return ZSTD_cpuid_bmi2(ZSTD_cpuid());
 }
+
+size_t ZSTD_getSequencesFromBlock(void const **sequences, void const *src,
+                                  size_t srcSize) {
+  void const *cLiterals;
+  ZSTD_LiteralsBlockType_e literalsType;
+  size_t const litSize =
+      ZSTD_getLiteralsFromBlock(&cLiterals, &literalsType, src, srcSize);
+  FORWARD_IF_ERROR(litSize, "corrupt literals");
+  uint8_t const *const seqStart = (uint8_t const *)cLiterals + litSize;
+  uint8_t const *const blockEnd = (uint8_t const *)src + srcSize;
+  RETURN_ERROR_IF(seqStart >= blockEnd, corruption_detected,
+                  "missing sequences section");
+  *sequences = seqStart;
+  return (size_t)(blockEnd - seqStart);
+}
+
+typedef struct {
+  size_t error;
+  ZSTD_FSETableCallback_t callback;
+  void *opaque;
+} ZSTD_ForEachFSETable_Data;
+
+static int ZSTD_forEachFSETable_callback(void *opaque, void const *block,
+                                         size_t blockSize,
+                                         ZSTD_BlockType_e type) {
+  ZSTD_ForEachFSETable_Data *data = (ZSTD_ForEachFSETable_Data *)opaque;
+  if (type != ZSTD_BlockType_compressed)
+    return 0;
+  void const *sequences;
+  size_t const seqSize = ZSTD_getSequencesFromBlock(&sequences, block, blockSize);
+  if (ZSTD_isError(seqSize)) {
+    data->error = seqSize;
+    return 1;
+  }
+  uint8_t const *ip = (uint8_t const *)sequences;
+  uint8_t const *const iend = ip + seqSize;
+
+  int const nbSeq = *ip++;
+  if (nbSeq == 0)
+    return 0;
+  if (nbSeq > 0x7F)
+    ip += nbSeq == 0xFF ? 2 : 1;
+  if (ip >= iend) {
+    data->error = ERROR(corruption_detected);
+    return 1;
+  }
+
+  uint8_t const modes = *ip++;
+  symbolEncodingType_e const types[3] = {
+      (symbolEncodingType_e)(modes >> 6),
+      (symbolEncodingType_e)((modes >> 4) & 3),
+      (symbolEncodingType_e)((modes >> 2) & 3),
+  };
+  unsigned const maxSymbols[3] = {MaxLL, MaxOff, MaxML};
+  for (int i = 0; i < 3; ++i) {
+    if (types[i] == set_rle) {
+      ++ip;
+    } else if (types[i] == set_compressed) {
+      short normalizedCounter[MaxSeq + 1];
+      unsigned maxSymbolValue = maxSymbols[i];
+      unsigned tableLog;
+      size_t const nCountSize =
+          FSE_readNCount(normalizedCounter, &maxSymbolValue, &tableLog, ip,
+                         (size_t)(iend - ip));
+      if (ZSTD_isError(nCountSize)) {
+        data->error = nCountSize;
+        return 1;
+      }
+      if (data->callback(data->opaque, ip, nCountSize, (ZSTD_FSETableType_e)i))
+        return 1;
+      ip += nCountSize;
+    }
+    if (ip > iend) {
+      data->error = ERROR(corruption_detected);
+      return 1;
+    }
+  }
+  return 0;
+}
+
+size_t ZSTD_forEachFSETable(void const *src, size_t srcSize,
+                            ZSTD_FSETableCallback_t callback, void *opaque) {
+  ZSTD_ForEachFSETable_Data data = {
+      .error = 0,
+      .callback = callback,
+      .opaque = opaque,
+  };
+  size_t const blocks =
+      ZSTD_forEachBlock(src, srcSize, ZSTD_forEachFSETable_callback, &data);
+  FORWARD_IF_ERROR(blocks, "for each block error");
+  FORWARD_IF_ERROR(data.error, "fse table error");
+  return blocks;
+}
+
+size_t ZSTD_FSE_sizeofCTableU32(unsigned maxSymbolValue, unsigned tableLog) {
+  return FSE_CTABLE_SIZE_U32(tableLog, maxSymbolValue);
+}
+
+size_t ZSTD_FSE_sizeofDTableU32(unsigned tableLog) {
+  return FSE_DTABLE_SIZE_U32(tableLog);
+}
+
+size_t ZSTD_FSE_sizeofWorkspaceU32(unsigned maxSymbolValue, unsigned tableLog) {
+#ifdef FSE_BUILD_CTABLE_WORKSPACE_SIZE_U32
+  size_t const cWorkspace =
+      FSE_BUILD_CTABLE_WORKSPACE_SIZE_U32(maxSymbolValue, tableLog);
+#else
+  size_t const cWorkspace = (1u << tableLog) + maxSymbolValue + 2;
+#endif
+#ifdef FSE_BUILD_DTABLE_WKSP_SIZE_U32
+  size_t const dWorkspace =
+      FSE_BUILD_DTABLE_WKSP_SIZE_U32(tableLog, maxSymbolValue);
+#else
+  size_t const dWorkspace = 0;
+#endif
+  return cWorkspace > dWorkspace ? cWorkspace : dWorkspace;
+}
+
+size_t ZSTD_FSE_readNCount(short *normalizedCounter, unsigned *maxSymbolValue,
+                           unsigned *tableLog, void const *src,
+                           size_t srcSize) {
+  return FSE_readNCount(normalizedCounter, maxSymbolValue, tableLog, src,
+                        srcSize);
+}
+
+size_t ZSTD_FSE_buildCTable(void *ctable, short const *normalizedCounter,
+                            unsigned maxSymbolValue, unsigned tableLog,
+                            void *workspace, size_t workspaceSize) {
+  return FSE_buildCTable_wksp((FSE_CTable *)ctable, normalizedCounter,
+                              maxSymbolValue, tableLog, workspace,
+                              workspaceSize);
+}
+
+size_t ZSTD_FSE_buildDTable(void *dtable, short const *normalizedCounter,
+                            unsigned maxSymbolValue, unsigned tableLog,
+                            void *workspace, size_t workspaceSize) {
+#ifdef FSE_BUILD_DTABLE_WKSP_SIZE_U32
+  return FSE_buildDTable_wksp((FSE_DTable *)dtable, normalizedCounter,
+                              maxSymbolValue, tableLog, workspace,
+                              workspaceSize);
+#else
+  (void)workspace;
+  (void)workspaceSize;
+  return FSE_buildDTable((FSE_DTable *)dtable, normalizedCounter,
+                         maxSymbolValue, tableLog);
+#endif
+}
+
+typedef struct {
+  size_t error;
+  ZSTD_SequencesBlockCallback_t callback;
+  void *opaque;
+} ZSTD_ForEachSequencesBlock_Data;
+
+static int ZSTD_forEachSequencesBlock_callback(void *opaque, void const *block,
+                                               size_t blockSize,
+                                               ZSTD_BlockType_e type) {
+  ZSTD_ForEachSequencesBlock_Data *data =
+      (ZSTD_ForEachSequencesBlock_Data *)opaque;
+  if (type != ZSTD_BlockType_compressed)
+    return 0;
+  void const *sequences;
+  size_t const seqSize =
+      ZSTD_getSequencesFromBlock(&sequences, block, blockSize);
+  if (ZSTD_isError(seqSize)) {
+    data->error = seqSize;
+    return 1;
+  }
+  return data->callback(data->opaque, sequences, seqSize);
+}
+
+size_t ZSTD_forEachSequencesBlock(void const *src, size_t srcSize,
+                                  ZSTD_SequencesBlockCallback_t callback,
+                                  void *opaque) {
+  ZSTD_ForEachSequencesBlock_Data data = {
+      .error = 0,
+      .callback = callback,
+      .opaque = opaque,
+  };
+  size_t const blocks = ZSTD_forEachBlock(
+      src, srcSize, ZSTD_forEachSequencesBlock_callback, &data);
+  FORWARD_IF_ERROR(blocks, "for each block error");
+  FORWARD_IF_ERROR(data.error, "sequences error");
+  return blocks;
+}
+
+size_t ZSTD_decodeSeqHeaders(ZSTD_DCtx *dctx, int *nbSeqPtr, const void *src,
+                             size_t srcSize);
+
+typedef struct {
+  size_t state;
+  ZSTD_seqSymbol const *table;
+} ZSTD_SeqState;
+
+static void ZSTD_SeqState_init(ZSTD_SeqState *state, BIT_DStream_t *bitD,
+                               ZSTD_seqSymbol const *dt) {
+  ZSTD_seqSymbol_header const *const header =
+      (ZSTD_seqSymbol_header const *)(void const *)dt;
+  state->state = BIT_readBits(bitD, header->tableLog);
+  BIT_reloadDStream(bitD);
+  state->table = dt + 1;
+}
+
+static void ZSTD_SeqState_update(ZSTD_SeqState *state, BIT_DStream_t *bitD) {
+  ZSTD_seqSymbol const cell = state->table[state->state];
+  state->state = cell.nextState + BIT_readBits(bitD, cell.nbBits);
+}
+
+// Decodes the sequences section of a compressed block for inspection,
+// following the format spec rather than zstd's decoder so that the decoded
+// sequences don't depend on its internals. The entropy tables and repcodes
+// are taken from and saved to dctx, so the sections of a frame must be
+// decoded in order after ZSTD_decompressBegin(). Literals are never touched.
+// Returns the number of bytes the sequences regenerate, excluding the last
+// literals. The callback is called on each sequence, and decoding stops with
+// *stopped set if it returns non-zero.
+static size_t ZSTD_inspectSequences(ZSTD_DCtx *dctx, void const *src,
+                                    size_t srcSize,
+                                    ZSTD_SequenceCallback_t callback,
+                                    void *opaque, int *stopped) {
+  int nbSeq;
+  size_t const seqHSize = ZSTD_decodeSeqHeaders(dctx, &nbSeq, src, srcSize);
+  FORWARD_IF_ERROR(seqHSize, "decode seq headers failed");
+  if (nbSeq == 0)
+    return 0;
+  dctx->fseEntropy = 1;
+
+  BIT_DStream_t bitD;
+  RETURN_ERROR_IF(ZSTD_isError(BIT_initDStream(
+                      &bitD, (uint8_t const *)src + seqHSize, srcSize - seqHSize)),
+                  corruption_detected, "bad sequences bitstream");
+  ZSTD_SeqState llState, ofState, mlState;
+  ZSTD_SeqState_init(&llState, &bitD, dctx->LLTptr);
+  ZSTD_SeqState_init(&ofState, &bitD, dctx->OFTptr);
+  ZSTD_SeqState_init(&mlState, &bitD, dctx->MLTptr);
+
+  uint32_t rep[ZSTD_REP_NUM];
+  for (int i = 0; i < ZSTD_REP_NUM; ++i)
+    rep[i] = dctx->entropy.rep[i];
+
+  size_t regenerated = 0;
+  for (int n = 0; n < nbSeq; ++n) {
+    ZSTD_seqSymbol const ll = llState.table[llState.state];
+    ZSTD_seqSymbol const ml = mlState.table[mlState.state];
+    // The offset code is the number of additional bits.
+    uint32_t const ofCode = ofState.table[ofState.state].nbAdditionalBits;
+
+    uint32_t const offsetValue =
+        (1u << ofCode) + (uint32_t)BIT_readBits(&bitD, ofCode);
+    BIT_reloadDStream(&bitD);
+    uint32_t const matchLength =
+        ml.baseValue + (uint32_t)BIT_readBits(&bitD, ml.nbAdditionalBits);
+    BIT_reloadDStream(&bitD);
+    uint32_t const litLength =
+        ll.baseValue + (uint32_t)BIT_readBits(&bitD, ll.nbAdditionalBits);
+    BIT_reloadDStream(&bitD);
+
+    uint32_t offset;
+    if (offsetValue > ZSTD_REP_NUM) {
+      offset = offsetValue - ZSTD_REP_NUM;
+      rep[2] = rep[1];
+      rep[1] = rep[0];
+      rep[0] = offset;
+    } else {
+      uint32_t const repIdx = offsetValue - 1 + (litLength == 0);
+      if (repIdx == 0) {
+        offset = rep[0];
+      } else {
+        offset = repIdx == ZSTD_REP_NUM ? rep[0] - 1 : rep[repIdx];
+        if (repIdx != 1)
+          rep[2] = rep[1];
+        rep[1] = rep[0];
+        rep[0] = offset;
+      }
+    }
+    regenerated += litLength + matchLength;
+
+    ZSTD_DecodedSequence const sequence = {
+        .litLength = litLength,
+        .matchLength = matchLength,
+        .offset = offset,
+        .repcode = offsetValue > ZSTD_REP_NUM ? 0 : offsetValue,
+    };
+    if (callback(opaque, &sequence)) {
+      *stopped = 1;
+      return regenerated;
+    }
+
+    if (n + 1 < nbSeq) {
+      ZSTD_SeqState_update(&llState, &bitD);
+      ZSTD_SeqState_update(&mlState, &bitD);
+      ZSTD_SeqState_update(&ofState, &bitD);
+      BIT_reloadDStream(&bitD);
+    }
+  }
+  RETURN_ERROR_IF(!BIT_endOfDStream(&bitD), corruption_detected,
+                  "sequences bitstream not fully consumed");
+
+  for (int i = 0; i < ZSTD_REP_NUM; ++i)
+    dctx->entropy.rep[i] = rep[i];
+  return regenerated;
+}
+
+// ZSTD_decodeSequence() lost its prefetch parameter in v1.5.0. Later
+// signatures aren't handled, since those revisions also changed the
+// ZSTD_decodeLiteralsBlock() the literals hooks above call.
+#if ZSTD_VERSION_NUMBER >= 10500
+#define ZSTD_decodeSequence_compat(state, longOffsets)                         \
+  ZSTD_decodeSequence(state, longOffsets)
+#else
+#define ZSTD_decodeSequence_compat(state, longOffsets)                         \
+  ZSTD_decodeSequence(state, longOffsets, ZSTD_p_noPrefetch)
+#endif
+
+// Decodes the sequences section of a compressed block with zstd's own
+// decoder, like ZSTD_decompressSequences() but without executing the
+// sequences. The entropy tables and repcodes are taken from and saved to
+// dctx, so the sections of a frame must be decoded in order after
+// ZSTD_decompressBegin(). Returns the number of bytes the sequences
+// regenerate, excluding the last literals.
+size_t ZSTD_decodeSequences(ZSTD_DCtx *dctx, void const *src, size_t srcSize) {
+#ifndef ZSTD_BENCH_DECOMPRESS_BLOCK_C
+  (void)dctx;
+  (void)src;
+  (void)srcSize;
+  RETURN_ERROR(GENERIC, "decompress/zstd_decompress_block.c not found");
+#else
+  int nbSeq;
+  size_t const seqHSize = ZSTD_decodeSeqHeaders(dctx, &nbSeq, src, srcSize);
+  FORWARD_IF_ERROR(seqHSize, "decode seq headers failed");
+  if (nbSeq == 0)
+    return 0;
+  dctx->fseEntropy = 1;
+
+  // Blocks don't carry the window size, so assume offsets may be long
+  // whenever zstd would check.
+  ZSTD_longOffset_e const isLongOffset =
+      MEM_32bits() ? ZSTD_lo_isLongOffset : ZSTD_lo_isRegularOffset;
+  seqState_t seqState;
+  for (int i = 0; i < ZSTD_REP_NUM; ++i)
+    seqState.prevOffset[i] = dctx->entropy.rep[i];
+  RETURN_ERROR_IF(ZSTD_isError(BIT_initDStream(&seqState.DStream,
+                                               (BYTE const *)src + seqHSize,
+                                               srcSize - seqHSize)),
+                  corruption_detected, "bad sequences bitstream");
+  ZSTD_initFseState(&seqState.stateLL, &seqState.DStream, dctx->LLTptr);
+  ZSTD_initFseState(&seqState.stateOffb, &seqState.DStream, dctx->OFTptr);
+  ZSTD_initFseState(&seqState.stateML, &seqState.DStream, dctx->MLTptr);
+
+  size_t regenerated = 0;
+  for (int n = 0; n < nbSeq; ++n) {
+    seq_t const sequence =
+        ZSTD_decodeSequence_compat(&seqState, isLongOffset);
+    regenerated += sequence.litLength + sequence.matchLength;
+  }
+  RETURN_ERROR_IF(BIT_reloadDStream(&seqState.DStream) < BIT_DStream_completed,
+                  corruption_detected, "sequences bitstream not fully consumed");
+
+  for (int i = 0; i < ZSTD_REP_NUM; ++i)
+    dctx->entropy.rep[i] = (U32)seqState.prevOffset[i];
+  return regenerated;
+#endif
+}
+
+typedef struct {
+  ZSTD_DCtx *dctx;
+  size_t error;
+  ZSTD_SequenceCallback_t callback;
+  void *opaque;
+} ZSTD_ForEachSequence_Data;
+
+static int ZSTD_forEachSequence_callback(void *opaque, void const *sequences,
+                                         size_t sequencesSize) {
+  ZSTD_ForEachSequence_Data *data = (ZSTD_ForEachSequence_Data *)opaque;
+  int stopped = 0;
+  size_t const ret =
+      ZSTD_inspectSequences(data->dctx, sequences, sequencesSize,
+                            data->callback, data->opaque, &stopped);
+  if (ZSTD_isError(ret)) {
+    data->error = ret;
+    return 1;
+  }
+  return stopped;
+}
+
+size_t ZSTD_forEachSequence(void const *src, size_t srcSize,
+                            ZSTD_SequenceCallback_t callback, void *opaque) {
+  ZSTD_ForEachSequence_Data data = {
+      .dctx = ZSTD_createDCtx(),
+      .error = 0,
+      .callback = callback,
+      .opaque = opaque,
+  };
+  RETURN_ERROR_IF(data.dctx == NULL, memory_allocation, "OOM");
+  size_t const begin = ZSTD_decompressBegin(data.dctx);
+  if (ZSTD_isError(begin)) {
+    ZSTD_freeDCtx(data.dctx);
+    return begin;
+  }
+  size_t const blocks = ZSTD_forEachSequencesBlock(
+      src, srcSize, ZSTD_forEachSequence_callback, &data);
+  ZSTD_freeDCtx(data.dctx);
+  FORWARD_IF_ERROR(blocks, "for each sequences block error");
+  FORWARD_IF_ERROR(data.error, "sequences error");
+  return blocks;
+}
+
+// Like ZSTD_compressBegin(), but with the parameters of `level` for a source
+// of `srcSize` bytes, like a one-shot compression of the same source would
+// use, instead of the parameters for an unknown size.
+size_t ZSTD_compressBeginForSize(ZSTD_CCtx *cctx, int level, size_t srcSize) {
+  ZSTD_parameters const params = ZSTD_getParams(level, srcSize, 0);
+  return ZSTD_compressBegin_advanced(cctx, NULL, 0, params, srcSize);
+}